@@ -61,11 +61,13 @@ fn main() -> Result<()> {
         .route(Method::GET, "/", HelloService)
         .route(Method::GET, "/greet/:name", GreetService)
         .route(Method::GET, "/custom", CustomService);
+    app.validate()?;
 
     let _custom_state_app = App::new(Router::new(), "Hello from state!".to_string())
         .route(Method::GET, "/state", StateService);
 
     let mut runtime = monoio::RuntimeBuilder::<monoio::LegacyDriver>::new()
+        .enable_timer()
         .build()
         .unwrap();
 
@@ -1,13 +1,15 @@
 use crate::{
-    http::{Request, Response},
+    http::{BodyMode, CookieKeys, Request, Response},
     error::Result,
-    service::{Service, Router, Node},
+    service::{Service, Router, Node, Route, RouteNode, RouteInfo, MethodRouter, MethodEndpoint, PathPolicy},
 };
 use http;
+use std::rc::Rc;
 
 pub struct App<State, R = Router<State>> {
     service: R,
     state: State,
+    cookie_keys: Option<Rc<CookieKeys>>,
 }
 
 impl<State, R: Service<State>> App<State, R> {
@@ -15,13 +17,28 @@ impl<State, R: Service<State>> App<State, R> {
         Self {
             service,
             state,
+            cookie_keys: None,
         }
     }
 
-    pub async fn handle(&self, req: Request) -> Result<Response> {
+    /// Keys for the signed and private cookies of `Request::cookie_jar`.
+    pub fn cookie_keys(mut self, keys: CookieKeys) -> Self {
+        self.cookie_keys = Some(Rc::new(keys));
+        self
+    }
+
+    pub async fn handle(&self, mut req: Request) -> Result<Response> {
+        if let Some(keys) = &self.cookie_keys {
+            req.extensions_mut().insert(keys.clone());
+        }
         self.service.call(req, &self.state).await
     }
 
+    /// See `Service::before_body`.
+    pub async fn before_body(&self, req: &Request) -> Result<BodyMode> {
+        self.service.before_body(req, &self.state).await
+    }
+
     pub fn service(&self) -> &R {
         &self.service
     }
@@ -36,6 +53,7 @@ impl<R: Service<()>> App<(), R> {
         Self {
             service,
             state: (),
+            cookie_keys: None,
         }
     }
 }
@@ -47,10 +65,72 @@ impl Default for App<()> {
 }
 
 impl<State, N> App<State, Router<State, N>> {
-    pub fn route<H: Service<State>>(self, method: http::Method, path: &str, handler: H) -> App<State, Router<State, Node<H, N>>> {
+    pub fn path_policy(self, policy: PathPolicy) -> Self {
+        App {
+            service: self.service.path_policy(policy),
+            state: self.state,
+            cookie_keys: self.cookie_keys,
+        }
+    }
+
+    /// Routes `path` through a [`MethodRouter`], see [`Router::at`].
+    pub fn at<M: MethodEndpoint<State>>(self, path: &str, methods: MethodRouter<State, M>) -> App<State, Router<State, Node<M, N>>> {
+        App {
+            service: self.service.at(path, methods),
+            state: self.state,
+            cookie_keys: self.cookie_keys,
+        }
+    }
+
+    pub fn route<H: Service<State>>(self, method: http::Method, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
         App {
             service: self.service.route(method, path, handler),
             state: self.state,
+            cookie_keys: self.cookie_keys,
         }
     }
-}
\ No newline at end of file
+
+    pub fn get<H: Service<State>>(self, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
+        self.route(http::Method::GET, path, handler)
+    }
+
+    pub fn post<H: Service<State>>(self, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
+        self.route(http::Method::POST, path, handler)
+    }
+
+    pub fn put<H: Service<State>>(self, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
+        self.route(http::Method::PUT, path, handler)
+    }
+
+    pub fn delete<H: Service<State>>(self, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
+        self.route(http::Method::DELETE, path, handler)
+    }
+
+    pub fn patch<H: Service<State>>(self, path: &str, handler: H) -> App<State, Router<State, Route<H, N>>> {
+        self.route(http::Method::PATCH, path, handler)
+    }
+}
+
+impl<State, M, N> App<State, Router<State, Node<M, N>>> {
+    pub fn name(self, name: &str) -> Self {
+        App {
+            service: self.service.name(name),
+            state: self.state,
+            cookie_keys: self.cookie_keys,
+        }
+    }
+}
+
+impl<State, N: RouteNode<State>> App<State, Router<State, N>> {
+    pub fn routes(&self) -> Vec<RouteInfo<'_>> {
+        self.service.routes()
+    }
+
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String> {
+        self.service.url_for(name, params)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.service.validate()
+    }
+}
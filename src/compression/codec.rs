@@ -0,0 +1,110 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use flate2::Compression;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+/// A content coding supported for `Content-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Gzip,
+    /// The zlib format, as HTTP's `deflate` is defined.
+    Deflate,
+    Brotli,
+}
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// Parses a coding token; `x-gzip` is accepted as an alias.
+    pub fn from_token(token: &str) -> Option<Self> {
+        let token = token.trim();
+        if token.eq_ignore_ascii_case("gzip") || token.eq_ignore_ascii_case("x-gzip") {
+            Some(Encoding::Gzip)
+        } else if token.eq_ignore_ascii_case("deflate") {
+            Some(Encoding::Deflate)
+        } else if token.eq_ignore_ascii_case("br") {
+            Some(Encoding::Brotli)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn encode(self, data: &[u8], level: Level) -> io::Result<Vec<u8>> {
+        let out = Vec::with_capacity(data.len() / 2);
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(out, level.flate());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(out, level.flate());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(out, 4096, level.brotli(), 22);
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(encoder.into_inner())
+            }
+        }
+    }
+
+    /// Decodes `data`, failing with `InvalidData` on corrupt input and
+    /// returning `None` once the output would exceed `limit` bytes.
+    pub(crate) fn decode(self, data: &[u8], limit: usize) -> io::Result<Option<Vec<u8>>> {
+        let reader: Box<dyn Read + '_> = match self {
+            Encoding::Gzip => Box::new(MultiGzDecoder::new(data)),
+            Encoding::Deflate => Box::new(ZlibDecoder::new(data)),
+            Encoding::Brotli => Box::new(brotli::Decompressor::new(data, 4096)),
+        };
+
+        let mut out = Vec::with_capacity(data.len().saturating_mul(4).min(limit));
+        reader.take(limit as u64 + 1).read_to_end(&mut out)?;
+        Ok((out.len() <= limit).then_some(out))
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Trade-off between compression speed and size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Level {
+    Fastest,
+    /// Suited to responses generated per request.
+    #[default]
+    Default,
+    /// Suited to content compressed once and served many times.
+    Best,
+}
+
+impl Level {
+    fn flate(self) -> Compression {
+        match self {
+            Level::Fastest => Compression::fast(),
+            Level::Default => Compression::default(),
+            Level::Best => Compression::best(),
+        }
+    }
+
+    fn brotli(self) -> u32 {
+        match self {
+            Level::Fastest => 1,
+            Level::Default => 4,
+            Level::Best => 11,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use http::{HeaderValue, header};
+
+use crate::{
+    error::{Error, Result},
+    http::{BodyMode, Request, Response},
+    service::Service,
+};
+use super::codec::Encoding;
+
+/// Decodes request bodies sent with `Content-Encoding` before the inner
+/// service sees them.
+///
+/// Unknown codings are rejected with `Error::UnsupportedMediaType` (415)
+/// and bodies that inflate beyond `max_size` with `Error::PayloadTooLarge`
+/// (413), which keeps compression bombs from exhausting memory.
+pub struct DecompressionService<Svc> {
+    inner: Svc,
+    max_size: usize,
+}
+
+impl<Svc> DecompressionService<Svc> {
+    pub fn new(inner: Svc) -> Self {
+        Self {
+            inner,
+            max_size: 8 * 1024 * 1024,
+        }
+    }
+
+    /// Largest decompressed body accepted, 8 MiB by default.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    fn decompress(&self, req: &mut Request) -> Result<()> {
+        let codings = req.headers()
+            .get_all(header::CONTENT_ENCODING)
+            .iter()
+            .map(|v| v.to_str().map_err(|_| Error::BadRequest("invalid Content-Encoding".into())))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+            .map(|coding| {
+                Encoding::from_token(coding)
+                    .ok_or_else(|| Error::UnsupportedMediaType(format!("unsupported content coding: {}", coding)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if codings.is_empty() {
+            req.headers_mut().remove(header::CONTENT_ENCODING);
+            return Ok(());
+        }
+
+        // Codings are listed in the order they were applied.
+        let mut body = req.body().to_vec();
+        for encoding in codings.into_iter().rev() {
+            body = encoding.decode(&body, self.max_size)
+                .map_err(|e| Error::BadRequest(format!("malformed {} body: {}", encoding, e)))?
+                .ok_or_else(|| Error::PayloadTooLarge(format!("decompressed body exceeds {} bytes", self.max_size)))?;
+        }
+
+        let headers = req.headers_mut();
+        headers.remove(header::CONTENT_ENCODING);
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        req.set_body(&body[..]);
+        Ok(())
+    }
+}
+
+impl<S, Svc: Service<S>> Service<S> for DecompressionService<Svc> {
+    async fn call(&self, mut req: Request, state: &S) -> Result<Response> {
+        self.decompress(&mut req)?;
+        self.inner.call(req, state).await
+    }
+
+    /// Encoded bodies are always buffered so they can be decoded; the
+    /// inner service may still read the result with `take_body_stream`.
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        let mode = self.inner.before_body(req, state).await?;
+        Ok(if is_encoded(req) { BodyMode::Buffer } else { mode })
+    }
+}
+
+/// Whether the body carries a coding other than `identity`.
+fn is_encoded(req: &Request) -> bool {
+    req.headers()
+        .get_all(header::CONTENT_ENCODING)
+        .iter()
+        .flat_map(|v| v.to_str().unwrap_or("x").split(','))
+        .map(str::trim)
+        .any(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+}
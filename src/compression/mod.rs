@@ -0,0 +1,239 @@
+mod codec;
+mod decompress;
+
+pub use codec::{Encoding, Level};
+pub use decompress::DecompressionService;
+
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+
+use crate::{
+    error::Result,
+    http::{BodyMode, Request, Response},
+    service::Service,
+};
+
+/// Settings for [`CompressionService`].
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    encodings: Vec<Encoding>,
+    min_size: usize,
+    max_file_size: u64,
+    level: Level,
+    content_types: Vec<String>,
+}
+
+impl CompressionConfig {
+    pub fn new() -> Self {
+        Self {
+            encodings: vec![Encoding::Brotli, Encoding::Gzip, Encoding::Deflate],
+            min_size: 1024,
+            max_file_size: 256 * 1024,
+            level: Level::default(),
+            content_types: [
+                "text/*",
+                "application/json",
+                "application/*+json",
+                "application/javascript",
+                "application/xml",
+                "application/*+xml",
+                "image/svg+xml",
+                "application/wasm",
+            ].iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Codings offered, in order of preference when the client rates
+    /// several equally.
+    pub fn encodings(mut self, encodings: &[Encoding]) -> Self {
+        self.encodings = encodings.to_vec();
+        self
+    }
+
+    /// Bodies smaller than this are sent as they are.
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Largest file body, such as those of `ServeDir`, that is compressed,
+    /// 256 KiB by default. There is no streaming encoder: the whole file is
+    /// read into memory and compressed in one go, so keep this small. Larger
+    /// files are sent uncompressed; serve precompressed variants for them.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Media types worth compressing. Entries may use `type/*` or a
+    /// `type/*+suffix` pattern; responses without `Content-Type` are skipped.
+    pub fn content_types(mut self, content_types: &[&str]) -> Self {
+        self.content_types = content_types.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn is_compressible(&self, content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        self.content_types.iter().any(|pattern| media_type_matches(pattern, &essence))
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn media_type_matches(pattern: &str, essence: &str) -> bool {
+    let (Some((pattern_type, pattern_sub)), Some((ty, sub))) = (pattern.split_once('/'), essence.split_once('/')) else {
+        return false;
+    };
+    if !pattern_type.eq_ignore_ascii_case(ty) {
+        return false;
+    }
+    match pattern_sub.strip_prefix('*') {
+        Some("") => true,
+        Some(suffix) => sub.len() > suffix.len() && sub[sub.len() - suffix.len()..].eq_ignore_ascii_case(suffix),
+        None => pattern_sub.eq_ignore_ascii_case(sub),
+    }
+}
+
+/// Compresses response bodies with the coding the client prefers, as
+/// negotiated through `Accept-Encoding`.
+///
+/// Only responses with a compressible `Content-Type`, a body of at least
+/// `min_size` bytes (and for file bodies at most `max_file_size`) and no
+/// existing `Content-Encoding` or `Content-Range` are touched, and
+/// `Cache-Control: no-transform` is honoured. Such responses always get
+/// `Vary: Accept-Encoding` so caches keep the variants apart.
+pub struct CompressionService<Svc> {
+    inner: Svc,
+    config: CompressionConfig,
+}
+
+impl<Svc> CompressionService<Svc> {
+    pub fn new(inner: Svc) -> Self {
+        Self {
+            inner,
+            config: CompressionConfig::default(),
+        }
+    }
+
+    pub fn config(mut self, config: CompressionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    fn negotiate(&self, req: &Request) -> Option<Encoding> {
+        let mut offers = self.config.encodings.iter().map(Encoding::as_str).collect::<Vec<_>>();
+        offers.push("identity");
+        req.negotiate_encoding(&offers).ok().and_then(Encoding::from_token)
+    }
+
+    async fn compress(&self, encoding: Option<Encoding>, resp: &mut Response) -> Result<()> {
+        if !self.is_eligible(resp) {
+            return Ok(());
+        }
+        add_vary(resp.headers_mut());
+
+        let Some(encoding) = encoding else {
+            return Ok(());
+        };
+        if let Some(region) = resp.file_body() {
+            if region.len() < self.config.min_size as u64 || region.len() > self.config.max_file_size {
+                return Ok(());
+            }
+            let body = region.read().await?;
+            resp.set_body(&body[..]);
+        }
+        if resp.body().len() < self.config.min_size {
+            return Ok(());
+        }
+
+        let compressed = encoding.encode(resp.body(), self.config.level)?;
+        if compressed.len() >= resp.body().len() {
+            return Ok(());
+        }
+
+        let headers = resp.headers_mut();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+        headers.remove(header::CONTENT_LENGTH);
+        weaken_etag(headers);
+        resp.set_body(&compressed[..]);
+        Ok(())
+    }
+
+    fn is_eligible(&self, resp: &Response) -> bool {
+        let headers = resp.headers();
+        let status = resp.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+            || status == StatusCode::PARTIAL_CONTENT
+        {
+            return false;
+        }
+        if headers.contains_key(header::CONTENT_ENCODING) || headers.contains_key(header::CONTENT_RANGE) {
+            return false;
+        }
+        let no_transform = headers.get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"));
+        if no_transform {
+            return false;
+        }
+        headers.get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|content_type| self.config.is_compressible(content_type))
+    }
+}
+
+/// Adds `Accept-Encoding` to `Vary` unless already covered.
+fn add_vary(headers: &mut HeaderMap) {
+    let covered = headers.get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|name| {
+            let name = name.trim();
+            name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+        });
+    if !covered {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+/// The compressed body is no longer byte-for-byte identical, so a strong
+/// validator becomes weak.
+fn weaken_etag(headers: &mut HeaderMap) {
+    let weak = match headers.get(header::ETAG) {
+        Some(etag) if !etag.as_bytes().starts_with(b"W/") => {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            HeaderValue::from_bytes(&weak).ok()
+        }
+        _ => None,
+    };
+    if let Some(weak) = weak {
+        headers.insert(header::ETAG, weak);
+    }
+}
+
+impl<S, Svc: Service<S>> Service<S> for CompressionService<Svc> {
+    async fn call(&self, req: Request, state: &S) -> Result<Response> {
+        let encoding = self.negotiate(&req);
+        let mut resp = self.inner.call(req, state).await?;
+        self.compress(encoding, &mut resp).await?;
+        Ok(resp)
+    }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        self.inner.before_body(req, state).await
+    }
+}
@@ -1,5 +1,10 @@
 use thiserror::Error;
+use std::fmt;
 use std::io;
+use http::{HeaderValue, StatusCode, header};
+use serde_json::Value;
+
+use crate::http::{Problem, Response};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -8,8 +13,9 @@ pub enum Error {
     #[error("Not found")]
     NotFound,
     
+    /// Carries the methods the resource supports, sent as `Allow`.
     #[error("Method not allowed")]
-    MethodNotAllowed,
+    MethodNotAllowed(Vec<http::Method>),
     
     #[error("Bad request: {0}")]
     BadRequest(String),
@@ -20,12 +26,36 @@ pub enum Error {
     #[error("Forbidden: {0}")]
     Forbidden(String),
     
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+    
+    #[error("Invalid query string: {0}")]
+    InvalidQuery(String),
+    
+    #[error("Not acceptable: {0}")]
+    NotAcceptable(String),
+    
+    #[error("Expectation failed: {0}")]
+    ExpectationFailed(String),
+    
     #[error("Internal server error: {0}")]
     InternalServerError(String),
     
     #[error("Internal error: {0}")]
     Internal(String),
     
+    #[error("Route conflict: {0}")]
+    RouteConflict(String),
+    
+    #[error("URL generation error: {0}")]
+    UrlGeneration(String),
+    
+    #[error("Invalid cookie: {0}")]
+    InvalidCookie(String),
+    
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
     
@@ -35,6 +65,228 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
     
+    #[error("Form error: {0}")]
+    Form(#[from] serde_urlencoded::de::Error),
+    
     #[error("HTTP parse error: {0}")]
     HttpParse(#[from] httparse::Error),
+    
+    /// An application error, see [`ResponseError`].
+    #[error(transparent)]
+    App(AppError),
+}
+
+impl Error {
+    /// Wraps an application error, keeping its type and source chain.
+    pub fn app(err: impl ResponseError) -> Self {
+        Error::App(AppError(Box::new(err)))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            Error::BadRequest(_) | Error::InvalidQuery(_) | Error::Form(_) | Error::HttpParse(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            Error::ExpectationFailed(_) => StatusCode::EXPECTATION_FAILED,
+            Error::App(err) => err.status(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The problem document describing this error, with nothing withheld.
+    pub fn problem(&self) -> Problem {
+        match self {
+            Error::App(err) => err.problem(),
+            err => Problem::new(err.status()).detail(err.to_string()),
+        }
+    }
+
+    /// The response sent to the client when this error reaches the server.
+    ///
+    /// Application errors with their own `ResponseError::render` are always
+    /// rendered by it. Otherwise, unless `expose_details` is set, server
+    /// errors render as a bare problem for their status so internals do not
+    /// leak; client errors are always rendered in full.
+    pub fn render(&self, expose_details: bool) -> Response {
+        if let Error::App(err) = self
+            && let Some(res) = err.render()
+        {
+            return res;
+        }
+
+        let status = self.status();
+        if status.is_server_error() && !expose_details {
+            return Problem::new(status).to_response();
+        }
+        match self {
+            Error::MethodNotAllowed(methods) => {
+                let mut res = self.problem().to_response();
+                if let Ok(allow) = HeaderValue::try_from(allow_header(methods)) {
+                    res.headers_mut().insert(header::ALLOW, allow);
+                }
+                res
+            }
+            err => err.problem().to_response(),
+        }
+    }
+
+    /// The application error of type `E`, if this is one.
+    pub fn downcast_ref<E: ResponseError>(&self) -> Option<&E> {
+        match self {
+            Error::App(err) => err.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+fn allow_header(methods: &[http::Method]) -> String {
+    methods.iter().map(http::Method::as_str).collect::<Vec<_>>().join(", ")
+}
+
+impl<E: ResponseError> From<E> for Error {
+    fn from(err: E) -> Self {
+        Error::app(err)
+    }
+}
+
+/// An error type that knows how to present itself to clients.
+///
+/// Handlers can return such errors with `?`; they travel up as
+/// [`Error::App`] so layers can still inspect them, and are rendered when
+/// they reach the server.
+pub trait ResponseError: std::error::Error + 'static {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    /// A stable, machine-readable identifier such as `"user_not_found"`.
+    fn code(&self) -> Option<&str> {
+        None
+    }
+
+    fn details(&self) -> Option<Value> {
+        None
+    }
+
+    /// Describes the error as `application/problem+json`, with `code` and
+    /// `details` as extension members.
+    fn problem(&self) -> Problem {
+        let mut problem = Problem::new(self.status()).detail(self.to_string());
+        if let Some(code) = self.code() {
+            problem = problem.extension("code", code);
+        }
+        if let Some(details) = self.details() {
+            problem = problem.extension("details", details);
+        }
+        problem
+    }
+
+    /// A response replacing the problem document. It is sent as-is for
+    /// every status, so it must not reveal more than intended.
+    fn render(&self) -> Option<Response> {
+        None
+    }
+}
+
+/// A boxed [`ResponseError`], as carried by [`Error::App`].
+pub struct AppError(Box<dyn ResponseError>);
+
+impl AppError {
+    pub fn status(&self) -> StatusCode { self.0.status() }
+    pub fn code(&self) -> Option<&str> { self.0.code() }
+    pub fn details(&self) -> Option<Value> { self.0.details() }
+    pub fn problem(&self) -> Problem { self.0.problem() }
+    pub fn render(&self) -> Option<Response> { self.0.render() }
+
+    pub fn downcast_ref<E: ResponseError>(&self) -> Option<&E> {
+        let err: &dyn std::error::Error = self.0.as_ref();
+        err.downcast_ref()
+    }
+}
+
+impl fmt::Debug for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// A ready-made [`ResponseError`] for when a dedicated type is overkill.
+///
+/// ```ignore
+/// Err(HttpError::new(StatusCode::CONFLICT, "email already registered")
+///     .code("email_taken")
+///     .details(json!({ "email": email })))?
+/// ```
+#[derive(Debug)]
+pub struct HttpError {
+    status: StatusCode,
+    message: String,
+    code: Option<String>,
+    details: Option<Value>,
+    source: Option<Box<dyn std::error::Error + 'static>>,
+}
+
+impl HttpError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            code: None,
+            details: None,
+            source: None,
+        }
+    }
+
+    pub fn code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Records the underlying cause for logging.
+    pub fn source(mut self, source: impl std::error::Error + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+impl ResponseError for HttpError {
+    fn status(&self) -> StatusCode { self.status }
+    fn code(&self) -> Option<&str> { self.code.as_deref() }
+    fn details(&self) -> Option<Value> { self.details.clone() }
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use bytes::{Bytes, BytesMut};
+use monoio::buf::IoBufMut;
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio::net::TcpStream;
+
+use crate::error::{Error, Result};
+
+/// Bytes read from the connection at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How a service wants to receive the body of a request, as decided by
+/// `Service::before_body`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyMode {
+    /// The whole body is read before `call` and available as
+    /// `Request::body`.
+    #[default]
+    Buffer,
+    /// The body is left on the connection for `call` to read with
+    /// `Request::take_body_stream`.
+    Stream,
+}
+
+/// A request body handed out chunk by chunk as it is read.
+pub struct BodyStream {
+    source: Source,
+}
+
+enum Source {
+    Memory(Option<Bytes>),
+    Connection(Rc<Incoming>),
+}
+
+impl BodyStream {
+    pub fn empty() -> Self {
+        Self { source: Source::Memory(None) }
+    }
+
+    pub fn from_bytes(bytes: impl Into<Bytes>) -> Self {
+        Self { source: Source::Memory(Some(bytes.into())) }
+    }
+
+    pub(crate) fn connection(incoming: Rc<Incoming>) -> Self {
+        Self { source: Source::Connection(incoming) }
+    }
+
+    /// The next chunk, or `None` once the body is exhausted.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        match &mut self.source {
+            Source::Memory(bytes) => Ok(bytes.take().filter(|b| !b.is_empty())),
+            Source::Connection(incoming) => incoming.read_chunk().await,
+        }
+    }
+
+    /// Reads the rest of the body into memory. Bodies longer than `limit`
+    /// fail with `Error::PayloadTooLarge` as soon as they pass it.
+    pub async fn collect(mut self, limit: usize) -> Result<BytesMut> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(Error::PayloadTooLarge(format!("body exceeds {} bytes", limit)));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+/// The unread body of the request on a connection, shared by the server
+/// and the request's [`BodyStream`].
+pub(crate) struct Incoming {
+    stream: Cell<Option<TcpStream>>,
+    buffered: Cell<BytesMut>,
+    remaining: Cell<u64>,
+    send_continue: Cell<bool>,
+}
+
+impl Incoming {
+    /// `buffered` holds the bytes that arrived with the request head.
+    pub(crate) fn new(stream: TcpStream, buffered: BytesMut, length: u64) -> Rc<Self> {
+        Rc::new(Self {
+            stream: Cell::new(Some(stream)),
+            buffered: Cell::new(buffered),
+            remaining: Cell::new(length),
+            send_continue: Cell::new(false),
+        })
+    }
+
+    /// Answers `Expect: 100-continue` with `100 Continue` right before the
+    /// body is first read from the socket.
+    pub(crate) fn expect_continue(&self) {
+        self.send_continue.set(true);
+    }
+
+    /// Whether the client still waits for `100 Continue`, in which case it
+    /// has not sent the body.
+    pub(crate) fn continue_pending(&self) -> bool {
+        self.send_continue.get()
+    }
+
+    /// Body bytes the client has yet to send, or has sent without them
+    /// being read.
+    pub(crate) fn unread_on_socket(&self) -> u64 {
+        let buffered = self.buffered.take();
+        let unread = self.remaining.get().saturating_sub(buffered.len() as u64);
+        self.buffered.set(buffered);
+        unread
+    }
+
+    /// Takes the connection back once the request is done with it.
+    pub(crate) fn take_stream(&self) -> Option<TcpStream> {
+        self.stream.take()
+    }
+
+    async fn read_chunk(&self) -> Result<Option<Bytes>> {
+        let remaining = self.remaining.get();
+        if remaining == 0 {
+            return Ok(None);
+        }
+
+        let mut buffered = self.buffered.take();
+        if !buffered.is_empty() {
+            let n = (buffered.len() as u64).min(remaining) as usize;
+            let chunk = buffered.split_to(n).freeze();
+            self.buffered.set(buffered);
+            self.remaining.set(remaining - n as u64);
+            return Ok(Some(chunk));
+        }
+
+        // The stream is put back after the read; if this future is dropped
+        // midway the connection is abandoned.
+        let mut stream = self.stream.take()
+            .ok_or_else(|| Error::Internal("request body is no longer readable".into()))?;
+        let result = self.read_socket(&mut stream, remaining).await;
+        self.stream.set(Some(stream));
+        result.map(Some)
+    }
+
+    async fn read_socket(&self, stream: &mut TcpStream, remaining: u64) -> Result<Bytes> {
+        if self.send_continue.replace(false) {
+            let (result, _) = stream.write_all(&b"HTTP/1.1 100 Continue\r\n\r\n"[..]).await;
+            result?;
+        }
+
+        let len = remaining.min(CHUNK_SIZE as u64) as usize;
+        let (result, buf) = stream.read(BytesMut::with_capacity(len).slice_mut(..len)).await;
+        let n = result?;
+        if n == 0 {
+            return Err(Error::BadRequest("Incomplete request body".into()));
+        }
+        self.remaining.set(remaining - n as u64);
+        Ok(buf.into_inner().freeze())
+    }
+}
@@ -0,0 +1,225 @@
+use std::net::{IpAddr, SocketAddr};
+
+use http::HeaderMap;
+
+use crate::error::{Error, Result};
+
+/// Details of a TLS session, for acceptors that terminate TLS.
+#[derive(Debug, Clone, Default)]
+pub struct TlsInfo {
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    version: Option<String>,
+}
+
+impl TlsInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn server_name(&self) -> Option<&str> { self.server_name.as_deref() }
+    pub fn alpn_protocol(&self) -> Option<&[u8]> { self.alpn_protocol.as_deref() }
+    pub fn version(&self) -> Option<&str> { self.version.as_deref() }
+
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    pub fn with_alpn_protocol(mut self, protocol: impl Into<Vec<u8>>) -> Self {
+        self.alpn_protocol = Some(protocol.into());
+        self
+    }
+
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+}
+
+/// The connection a request arrived on, available through
+/// `Request::connection_info`.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    id: u64,
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    client_ip: IpAddr,
+    tls: Option<TlsInfo>,
+}
+
+impl ConnectionInfo {
+    pub fn new(id: u64, remote_addr: SocketAddr, local_addr: SocketAddr) -> Self {
+        Self {
+            id,
+            remote_addr,
+            local_addr,
+            client_ip: remote_addr.ip(),
+            tls: None,
+        }
+    }
+
+    /// Number of the connection, unique per server.
+    pub fn id(&self) -> u64 { self.id }
+    pub fn remote_addr(&self) -> SocketAddr { self.remote_addr }
+    pub fn local_addr(&self) -> SocketAddr { self.local_addr }
+
+    /// The remote address, or the originating client when the request came
+    /// through trusted proxies.
+    pub fn client_ip(&self) -> IpAddr { self.client_ip }
+
+    /// `None` for plain TCP connections.
+    pub fn tls(&self) -> Option<&TlsInfo> { self.tls.as_ref() }
+
+    pub fn with_client_ip(mut self, ip: IpAddr) -> Self {
+        self.client_ip = ip;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: TlsInfo) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(s: &str) -> Option<Self> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (s.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let mask = |bits: u32, prefix: u8| -> u128 {
+            if prefix == 0 { 0 } else { u128::MAX << (bits - prefix as u32) }
+        };
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let m = mask(32, self.prefix) as u32;
+                u32::from(net) & m == u32::from(ip) & m
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let m = mask(128, self.prefix);
+                u128::from(net) & m == u128::from(ip) & m
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The header trusted proxies report the client address in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForwardedHeader {
+    #[default]
+    XForwardedFor,
+    /// RFC 7239 `Forwarded`, using the `for` parameter of each element.
+    Forwarded,
+}
+
+/// Proxies allowed to report the client address via `X-Forwarded-For` or
+/// `Forwarded`.
+///
+/// Only the header named by [`TrustedProxies::header`] is read; the other
+/// one is ignored even when present, since clients can send it freely.
+/// Without any trusted proxy both are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Cidr>,
+    header: ForwardedHeader,
+}
+
+impl TrustedProxies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts an address (`10.0.0.1`) or network (`10.0.0.0/8`).
+    pub fn trust(mut self, network: &str) -> Result<Self> {
+        let cidr = Cidr::parse(network)
+            .ok_or_else(|| Error::Internal(format!("invalid proxy address: {}", network)))?;
+        self.networks.push(cidr);
+        Ok(self)
+    }
+
+    /// The header the proxies set, `X-Forwarded-For` by default.
+    pub fn header(mut self, header: ForwardedHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(ip))
+    }
+
+    /// Walks the forwarding chain from the nearest hop back, stopping at the
+    /// first address that is not a trusted proxy.
+    pub fn client_ip(&self, remote: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(remote) {
+            return remote;
+        }
+
+        let mut client = remote;
+        for hop in forwarded_chain(headers, self.header).iter().rev() {
+            match hop.as_deref().and_then(parse_node) {
+                Some(ip) => {
+                    client = ip;
+                    if !self.contains(ip) {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        client
+    }
+}
+
+/// The addresses in `header`, in order from the client towards us.
+/// `Forwarded` elements without a `for` parameter are kept as `None` so
+/// they still count as a hop.
+fn forwarded_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<String>> {
+    let name = match header {
+        ForwardedHeader::XForwardedFor => http::header::HeaderName::from_static("x-forwarded-for"),
+        ForwardedHeader::Forwarded => http::header::FORWARDED,
+    };
+    let elements = headers.get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim);
+
+    match header {
+        ForwardedHeader::XForwardedFor => elements.map(|node| Some(node.to_string())).collect(),
+        ForwardedHeader::Forwarded => elements
+            .map(|element| {
+                element.split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .map(|(_, value)| value.trim().trim_matches('"').to_string())
+            })
+            .collect(),
+    }
+}
+
+/// Parses `192.0.2.1`, `192.0.2.1:80`, `2001:db8::1` or `[2001:db8::1]:80`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    node.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .and_then(|(ip, _)| ip.parse().ok())
+}
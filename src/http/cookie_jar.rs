@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use super::cookie::{Cookie, parse_cookie_header};
+use super::cookie_keys::CookieKeys;
+use super::request::Request;
+use crate::error::Result;
+
+/// The cookies of a request plus the ones a handler sets, with the app's
+/// [`CookieKeys`] for signing and encrypting them.
+///
+/// Obtained from `Request::cookie_jar`; changes reach the client once the
+/// jar is passed to `Response::add_cookies`. Reads see changes made through
+/// the jar.
+///
+/// ```ignore
+/// let mut jar = req.cookie_jar()?;
+/// let visits = jar.private("visits").and_then(|v| v.parse().ok()).unwrap_or(0) + 1;
+/// jar.add_private(Cookie::new("visits", visits.to_string()).http_only(true))?;
+/// resp.add_cookies(&jar)?;
+/// ```
+pub struct CookieJar {
+    keys: Rc<CookieKeys>,
+    incoming: Vec<(String, String)>,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new(req: &Request, keys: Rc<CookieKeys>) -> Self {
+        let incoming = req.headers()
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_cookie_header)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Self {
+            keys,
+            incoming,
+            delta: Vec::new(),
+        }
+    }
+
+    /// The raw value of cookie `name`, `None` if it was removed.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        if let Some(cookie) = self.delta.iter().rev().find(|c| c.name() == name) {
+            return (!cookie.is_removal()).then_some(cookie.value());
+        }
+        self.incoming.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of a cookie set with [`CookieJar::add_signed`], if its
+    /// signature checks out.
+    pub fn signed(&self, name: &str) -> Option<String> {
+        self.get(name).and_then(|value| self.keys.verify(name, value))
+    }
+
+    /// The value of a cookie set with [`CookieJar::add_private`], if it
+    /// decrypts.
+    pub fn private(&self, name: &str) -> Option<String> {
+        self.get(name).and_then(|value| self.keys.decrypt(name, value))
+    }
+
+    pub fn add(&mut self, cookie: Cookie) -> Result<()> {
+        cookie.validate()?;
+        self.delta.push(cookie);
+        Ok(())
+    }
+
+    /// Adds a cookie whose value is readable by the client but tamper-proof.
+    pub fn add_signed(&mut self, cookie: Cookie) -> Result<()> {
+        let value = self.keys.sign(cookie.name(), cookie.value());
+        self.add(cookie.with_value(value))
+    }
+
+    /// Adds a cookie whose value is encrypted and authenticated.
+    pub fn add_private(&mut self, cookie: Cookie) -> Result<()> {
+        let value = self.keys.encrypt(cookie.name(), cookie.value());
+        self.add(cookie.with_value(value))
+    }
+
+    /// Tells the client to delete the cookie. Path and domain must match the
+    /// ones it was set with.
+    pub fn remove(&mut self, cookie: Cookie) -> Result<()> {
+        self.add(cookie.into_removal())
+    }
+
+    /// The cookies added or removed through the jar, in order.
+    pub fn delta(&self) -> impl Iterator<Item = &Cookie> {
+        self.delta.iter()
+    }
+}
@@ -0,0 +1,147 @@
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const NONCE_LEN: usize = 12;
+
+/// Secret used to sign and encrypt cookies.
+///
+/// Separate signing and encryption keys are derived from one master key of
+/// at least 32 bytes.
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl Key {
+    pub fn from_bytes(master: &[u8]) -> Result<Self> {
+        if master.len() < 32 {
+            return Err(Error::Internal("cookie key must be at least 32 bytes".into()));
+        }
+        Ok(Self {
+            signing: derive(master, b"sark cookie signing"),
+            encryption: derive(master, b"sark cookie encryption"),
+        })
+    }
+
+    pub fn generate() -> Self {
+        let master = Aes256Gcm::generate_key(OsRng);
+        Self::from_bytes(&master).expect("generated key is 32 bytes")
+    }
+
+    fn mac(&self, name: &str, value: &str) -> HmacSha256 {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.signing).expect("HMAC accepts any key length");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.encryption.into())
+    }
+}
+
+/// A URL-safe random string carrying `bytes` bytes of entropy.
+pub(crate) fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn derive(master: &[u8], purpose: &[u8]) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(master).expect("HMAC accepts any key length");
+    mac.update(purpose);
+    mac.finalize().into_bytes().into()
+}
+
+/// The key cookies are written with, plus retired keys still accepted when
+/// reading them, so keys can be rotated without logging everyone out.
+///
+/// Register them with `App::cookie_keys` to use them through
+/// `Request::cookie_jar`, or pass them to `Request::signed_cookie` /
+/// `Response::add_signed_cookie` and their private counterparts.
+#[derive(Clone)]
+pub struct CookieKeys {
+    current: Key,
+    previous: Vec<Key>,
+}
+
+impl CookieKeys {
+    pub fn new(current: Key) -> Self {
+        Self {
+            current,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Adds a retired key that is only used to verify and decrypt.
+    pub fn with_previous(mut self, key: Key) -> Self {
+        self.previous.push(key);
+        self
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Key> {
+        std::iter::once(&self.current).chain(self.previous.iter())
+    }
+
+    /// Prefixes `value` with an HMAC covering the cookie name and value.
+    pub fn sign(&self, name: &str, value: &str) -> String {
+        let tag = self.current.mac(name, value).finalize().into_bytes();
+        format!("{}.{}", URL_SAFE_NO_PAD.encode(tag), value)
+    }
+
+    /// Returns the original value if it was signed by any known key.
+    pub fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        let (tag, value) = signed.split_once('.')?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+        self.all()
+            .any(|key| key.mac(name, value).verify_slice(&tag).is_ok())
+            .then(|| value.to_string())
+    }
+
+    /// Encrypts and authenticates `value`, binding it to the cookie name.
+    pub fn encrypt(&self, name: &str, value: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: name.as_bytes(),
+        };
+        let ciphertext = self.current.cipher()
+            .encrypt(&nonce, payload)
+            .expect("AES-GCM encryption does not fail for cookie-sized input");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        URL_SAFE_NO_PAD.encode(sealed)
+    }
+
+    /// Returns the plaintext if it was encrypted by any known key.
+    pub fn decrypt(&self, name: &str, sealed: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::from_slice(nonce);
+
+        self.all()
+            .find_map(|key| {
+                let payload = Payload {
+                    msg: ciphertext,
+                    aad: name.as_bytes(),
+                };
+                key.cipher().decrypt(nonce, payload).ok()
+            })
+            .and_then(|plain| String::from_utf8(plain).ok())
+    }
+}
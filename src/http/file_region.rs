@@ -0,0 +1,192 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use bytes::Bytes;
+use monoio::fs::File;
+use monoio::io::AsyncWriteRentExt;
+use monoio::net::TcpStream;
+
+/// Bytes read from the file at a time.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// A byte range of an open file, used as a response body that the server
+/// sends without buffering it whole: on Linux with the io_uring driver it
+/// is spliced to the socket through a pipe, otherwise it is read and
+/// written in bounded chunks.
+#[derive(Clone)]
+pub struct FileRegion {
+    file: Rc<File>,
+    offset: u64,
+    len: u64,
+}
+
+impl FileRegion {
+    pub fn new(file: File, offset: u64, len: u64) -> Self {
+        Self {
+            file: Rc::new(file),
+            offset,
+            len,
+        }
+    }
+
+    /// The whole file at `path`.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path).await?;
+        let len = file.metadata().await?.len();
+        Ok(Self::new(file, 0, len))
+    }
+
+    /// The part of this region's file starting at `offset`, sharing the
+    /// open file.
+    pub fn slice(&self, offset: u64, len: u64) -> Self {
+        Self {
+            file: self.file.clone(),
+            offset,
+            len,
+        }
+    }
+
+    pub fn offset(&self) -> u64 { self.offset }
+    pub fn len(&self) -> u64 { self.len }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Reads the region into memory.
+    pub async fn read(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut buf = Vec::with_capacity(CHUNK_SIZE.min(self.len as usize));
+        let mut pos = self.offset;
+        while pos < self.offset + self.len {
+            let (result, read) = self.read_chunk(pos, buf).await;
+            buf = read;
+            result?;
+            out.extend_from_slice(&buf);
+            pos += buf.len() as u64;
+        }
+        Ok(out)
+    }
+
+    pub(crate) async fn write_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if !monoio::utils::is_legacy()
+            && let Ok(source) = splice::source(&self.file, self.offset)
+        {
+            return splice::send(source, self.len, stream).await;
+        }
+        self.copy_to(stream).await
+    }
+
+    /// Writes the region through a user-space buffer, for the legacy driver.
+    async fn copy_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(CHUNK_SIZE.min(self.len as usize));
+        let mut pos = self.offset;
+        while pos < self.offset + self.len {
+            let (result, read) = self.read_chunk(pos, buf).await;
+            buf = read;
+            result?;
+            pos += buf.len() as u64;
+
+            let (result, written) = stream.write_all(buf).await;
+            buf = written;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Reads the chunk at `pos` into `buf`, replacing its contents and
+    /// cutting it off at the end of the region.
+    async fn read_chunk(&self, pos: u64, mut buf: Vec<u8>) -> (io::Result<()>, Vec<u8>) {
+        buf.clear();
+        let (result, mut buf) = self.file.read_at(buf, pos).await;
+        let result = match result {
+            Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf.truncate(n.min((self.offset + self.len - pos) as usize));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+        (result, buf)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod splice {
+    use std::fs;
+    use std::io::{self, Seek, SeekFrom};
+    use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd};
+    use std::os::unix::net;
+
+    use monoio::fs::File;
+    use monoio::io::splice::{SpliceDestination, SpliceSource};
+    use monoio::net::{TcpStream, UnixStream, unix::new_pipe};
+
+    use super::CHUNK_SIZE;
+
+    /// A read handle for `file` positioned at `offset`.
+    ///
+    /// monoio's splice ops take no offset and only accept its socket types
+    /// as a source, so the file is reopened through procfs, which gives it
+    /// a file position of its own, and carried in a `UnixStream`, which
+    /// only hands the descriptor to the splice op. Under io_uring this does
+    /// not register the descriptor anywhere.
+    pub(super) fn source(file: &File, offset: u64) -> io::Result<UnixStream> {
+        let mut reopened = fs::File::open(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+        reopened.seek(SeekFrom::Start(offset))?;
+        // SAFETY: the descriptor was just opened and is owned by nothing else.
+        let carrier = unsafe { net::UnixStream::from_raw_fd(reopened.into_raw_fd()) };
+        UnixStream::from_std(carrier)
+    }
+
+    /// Moves `len` bytes from `source` to `stream` through a pipe, without
+    /// copying them into user space.
+    pub(super) async fn send(mut source: UnixStream, len: u64, stream: &mut TcpStream) -> io::Result<()> {
+        let (mut pipe_out, mut pipe_in) = new_pipe()?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let mut filled = source.splice_to_pipe(&mut pipe_in, remaining.min(CHUNK_SIZE as u64) as u32).await?;
+            if filled == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            remaining -= filled as u64;
+            while filled > 0 {
+                match stream.splice_from_pipe(&mut pipe_out, filled).await? {
+                    0 => return Err(io::ErrorKind::WriteZero.into()),
+                    sent => filled -= sent,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A piece of a body assembled from file regions and literal bytes, such as
+/// a `multipart/byteranges` response. See `Response::set_file_parts`.
+#[derive(Debug, Clone)]
+pub enum FilePart {
+    Bytes(Bytes),
+    Region(FileRegion),
+}
+
+impl FilePart {
+    pub fn len(&self) -> u64 {
+        match self {
+            FilePart::Bytes(bytes) => bytes.len() as u64,
+            FilePart::Region(region) => region.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for FileRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileRegion")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
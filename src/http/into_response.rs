@@ -0,0 +1,124 @@
+use bytes::{Bytes, BytesMut};
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+use serde::Serialize;
+
+use crate::error::{AppError, Error, ResponseError, Result};
+use super::response::Response;
+
+/// Conversion of handler return values into a [`Response`].
+///
+/// The conversion is fallible so that framework errors keep travelling up
+/// to the layers and the server: `Error` and every [`ResponseError`]
+/// convert to `Err`, so `Result<T, E>` works for application errors too.
+pub trait IntoResponse {
+    fn into_response(self) -> Result<Response>;
+}
+
+/// Serializes `T` as an `application/json` body.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Result<Response> {
+        Ok(self)
+    }
+}
+
+impl IntoResponse for () {
+    fn into_response(self) -> Result<Response> {
+        Ok(Response::ok())
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Result<Response> {
+        Ok(Response::new(self))
+    }
+}
+
+impl IntoResponse for &str {
+    fn into_response(self) -> Result<Response> {
+        Ok(Response::text(self))
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Result<Response> {
+        Ok(Response::text(self))
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self) -> Result<Response> {
+        Ok(octet_stream(BytesMut::from(self)))
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> Result<Response> {
+        Ok(octet_stream(BytesMut::from(&self[..])))
+    }
+}
+
+fn octet_stream(body: BytesMut) -> Response {
+    let mut res = Response::ok();
+    res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"));
+    res.set_body(body);
+    res
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Result<Response> {
+        let body = serde_json::to_vec(&self.0)?;
+        let mut res = Response::ok();
+        res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        res.set_body(&body[..]);
+        Ok(res)
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> Result<Response> {
+        let mut res = self.1.into_response()?;
+        res.set_status(self.0);
+        Ok(res)
+    }
+}
+
+/// Headers given here replace those of the same name set by `T`.
+impl<T: IntoResponse> IntoResponse for (StatusCode, HeaderMap, T) {
+    fn into_response(self) -> Result<Response> {
+        let mut res = (self.0, self.2).into_response()?;
+        res.headers_mut().extend(self.1);
+        Ok(res)
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for std::result::Result<T, E> {
+    fn into_response(self) -> Result<Response> {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Result<Response> {
+        Err(self)
+    }
+}
+
+/// Application errors travel up as [`Error::App`], so a handler can return
+/// `Result<T, E>` for its own `E: ResponseError`.
+impl<E: ResponseError> IntoResponse for E {
+    fn into_response(self) -> Result<Response> {
+        Err(Error::app(self))
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Result<Response> {
+        Err(Error::App(self))
+    }
+}
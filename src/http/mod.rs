@@ -1,5 +1,30 @@
 mod request;
 mod response;
+mod multipart;
+mod body;
+mod cookie;
+mod cookie_jar;
+pub(crate) mod cookie_keys;
+mod extensions;
+mod connection;
+mod query;
+mod negotiate;
+mod into_response;
+mod problem;
+mod file_region;
 
 pub use request::Request;
-pub use response::Response;
\ No newline at end of file
+pub use response::{Response, ResponseBuilder};
+pub use multipart::{Multipart, MultipartLimits, Part};
+pub use body::{BodyMode, BodyStream};
+pub(crate) use body::Incoming;
+pub use cookie::{Cookie, SameSite};
+pub use cookie_jar::CookieJar;
+pub use cookie_keys::{CookieKeys, Key};
+pub use extensions::Extensions;
+pub use connection::{ConnectionInfo, ForwardedHeader, TlsInfo, TrustedProxies};
+pub use query::Query;
+pub use negotiate::QualityItem;
+pub use into_response::{IntoResponse, Json};
+pub use problem::Problem;
+pub use file_region::{FilePart, FileRegion};
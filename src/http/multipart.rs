@@ -0,0 +1,311 @@
+use bytes::{Buf, Bytes, BytesMut};
+use http::{HeaderMap, HeaderName, HeaderValue, header};
+use httparse::{EMPTY_HEADER, Status};
+use percent_encoding::percent_decode_str;
+
+use crate::error::{Error, Result};
+use super::body::BodyStream;
+
+const MAX_PART_HEADERS: usize = 16;
+
+/// Largest header block accepted for a single part.
+const MAX_PART_HEADER_SIZE: usize = 8 * 1024;
+
+/// Size limits applied while reading a multipart body.
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+    part: usize,
+    total: usize,
+}
+
+impl MultipartLimits {
+    pub fn new() -> Self {
+        Self {
+            part: 1024 * 1024,
+            total: 8 * 1024 * 1024,
+        }
+    }
+
+    /// Maximum size of a single part's data.
+    pub fn part(mut self, limit: usize) -> Self {
+        self.part = limit;
+        self
+    }
+
+    /// Maximum size of all part data together.
+    pub fn total(mut self, limit: usize) -> Self {
+        self.total = limit;
+        self
+    }
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single field or file of a `multipart/form-data` body.
+pub struct Part {
+    headers: HeaderMap,
+    name: Option<String>,
+    filename: Option<String>,
+    data: Bytes,
+}
+
+impl Part {
+    pub fn headers(&self) -> &HeaderMap { &self.headers }
+    pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+    pub fn filename(&self) -> Option<&str> { self.filename.as_deref() }
+    pub fn data(&self) -> &[u8] { &self.data }
+    pub fn into_data(self) -> Bytes { self.data }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Preamble,
+    Parts,
+    Done,
+}
+
+/// Incremental parser for a `multipart/form-data` body.
+///
+/// The body is pulled from its [`BodyStream`] as parts are requested, so
+/// only the part being read is held in memory, and the limits are checked
+/// as its bytes arrive. Parsing stops after the first error.
+pub struct Multipart {
+    body: BodyStream,
+    buffer: BytesMut,
+    delimiter: Vec<u8>,
+    state: State,
+    total: usize,
+    limits: MultipartLimits,
+}
+
+impl Multipart {
+    pub fn new(body: BodyStream, boundary: &str) -> Self {
+        Self {
+            body,
+            buffer: BytesMut::new(),
+            delimiter: format!("--{}", boundary).into_bytes(),
+            state: State::Preamble,
+            total: 0,
+            limits: MultipartLimits::default(),
+        }
+    }
+
+    pub fn limits(mut self, limits: MultipartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The next part, or `None` after the closing boundary.
+    pub async fn next_part(&mut self) -> Result<Option<Part>> {
+        let result = self.read_part().await;
+        if !matches!(result, Ok(Some(_))) {
+            self.state = State::Done;
+        }
+        result
+    }
+
+    async fn read_part(&mut self) -> Result<Option<Part>> {
+        match self.state {
+            State::Done => return Ok(None),
+            State::Preamble => {
+                self.skip_preamble().await?;
+                self.state = State::Parts;
+            }
+            State::Parts => (),
+        }
+
+        // Positioned right after a delimiter.
+        while self.buffer.len() < 2 {
+            if !self.fill().await? {
+                return Err(malformed("unexpected end of body"));
+            }
+        }
+        if self.buffer.starts_with(b"--") {
+            return Ok(None);
+        }
+        if !self.buffer.starts_with(b"\r\n") {
+            return Err(malformed("missing line break after boundary"));
+        }
+        self.buffer.advance(2);
+
+        let headers = self.read_headers().await?;
+        let data = self.read_data().await?;
+
+        let disposition = headers.get(header::CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        let name = header_param(disposition, "name");
+        let filename = header_param(disposition, "filename*")
+            .and_then(|v| decode_ext_value(&v))
+            .or_else(|| header_param(disposition, "filename"));
+
+        Ok(Some(Part {
+            headers,
+            name,
+            filename,
+            data,
+        }))
+    }
+
+    /// Appends the next chunk of the body to the buffer; false at its end.
+    async fn fill(&mut self) -> Result<bool> {
+        match self.body.chunk().await? {
+            Some(chunk) => {
+                self.buffer.extend_from_slice(&chunk);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn skip_preamble(&mut self) -> Result<()> {
+        loop {
+            if let Some(i) = find(&self.buffer, &self.delimiter) {
+                self.buffer.advance(i + self.delimiter.len());
+                return Ok(());
+            }
+            // Keep just enough to spot a delimiter split across chunks.
+            let keep = (self.delimiter.len() - 1).min(self.buffer.len());
+            self.buffer.advance(self.buffer.len() - keep);
+            if !self.fill().await? {
+                return Err(malformed("missing boundary"));
+            }
+        }
+    }
+
+    async fn read_headers(&mut self) -> Result<HeaderMap> {
+        loop {
+            let mut raw_headers = [EMPTY_HEADER; MAX_PART_HEADERS];
+            if let Status::Complete((len, parsed)) = httparse::parse_headers(&self.buffer, &mut raw_headers)? {
+                let mut headers = HeaderMap::new();
+                for h in parsed {
+                    let name = HeaderName::from_bytes(h.name.as_bytes())
+                        .map_err(|_| malformed("invalid part header name"))?;
+                    let value = HeaderValue::from_bytes(h.value)
+                        .map_err(|_| malformed("invalid part header value"))?;
+                    headers.append(name, value);
+                }
+                self.buffer.advance(len);
+                return Ok(headers);
+            }
+
+            if self.buffer.len() > MAX_PART_HEADER_SIZE {
+                return Err(malformed("part headers too large"));
+            }
+            if !self.fill().await? {
+                return Err(malformed("incomplete part headers"));
+            }
+        }
+    }
+
+    /// Reads part data up to the next delimiter, which is consumed.
+    async fn read_data(&mut self) -> Result<Bytes> {
+        let mut end_delimiter = b"\r\n".to_vec();
+        end_delimiter.extend_from_slice(&self.delimiter);
+
+        let mut data = BytesMut::new();
+        loop {
+            let found = find(&self.buffer, &end_delimiter);
+            // Without a match, the tail may be the start of the delimiter.
+            let len = found.unwrap_or_else(|| self.buffer.len().saturating_sub(end_delimiter.len() - 1));
+
+            if data.len() + len > self.limits.part {
+                return Err(Error::PayloadTooLarge(format!(
+                    "multipart part exceeds {} bytes", self.limits.part
+                )));
+            }
+            self.total += len;
+            if self.total > self.limits.total {
+                return Err(Error::PayloadTooLarge(format!(
+                    "multipart body exceeds {} bytes", self.limits.total
+                )));
+            }
+            data.extend_from_slice(&self.buffer[..len]);
+            self.buffer.advance(len);
+
+            if found.is_some() {
+                self.buffer.advance(end_delimiter.len());
+                return Ok(data.freeze());
+            }
+            if !self.fill().await? {
+                return Err(malformed("unterminated part"));
+            }
+        }
+    }
+}
+
+fn malformed(reason: &str) -> Error {
+    Error::BadRequest(format!("Malformed multipart body: {}", reason))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Reads a parameter such as `boundary` or `filename` from a header value
+/// of the form `value; key=token; key="quoted"`.
+pub(crate) fn header_param(value: &str, key: &str) -> Option<String> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (name, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let (param, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut param = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                param.push(escaped);
+                            }
+                        }
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        c => param.push(c),
+                    }
+                }
+                let next = quoted[end..].split_once(';').map(|(_, n)| n);
+                (param, next)
+            }
+            None => match after.split_once(';') {
+                Some((token, next)) => (token.trim().to_string(), Some(next)),
+                None => (after.trim().to_string(), None),
+            },
+        };
+
+        if name.trim().eq_ignore_ascii_case(key) {
+            return Some(param);
+        }
+        rest = next?;
+    }
+}
+
+/// Decodes an RFC 5987 value such as `UTF-8''na%C3%AFve.txt`.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    if !charset.eq_ignore_ascii_case("utf-8") {
+        return None;
+    }
+    percent_decode_str(encoded).decode_utf8().ok().map(|s| s.into_owned())
+}
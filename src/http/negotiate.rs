@@ -0,0 +1,129 @@
+use http::HeaderMap;
+use http::header::HeaderName;
+
+/// One entry of an `Accept`-style header together with its q-value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityItem {
+    value: String,
+    quality: f32,
+}
+
+impl QualityItem {
+    pub fn value(&self) -> &str { &self.value }
+    pub fn quality(&self) -> f32 { self.quality }
+}
+
+/// Parses every value of `name`, ordered by descending quality. Entries
+/// with equal quality keep their header order.
+pub(crate) fn parse_header(headers: &HeaderMap, name: HeaderName) -> Vec<QualityItem> {
+    let mut items = headers.get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(parse_item)
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+    items
+}
+
+fn parse_item(item: &str) -> Option<QualityItem> {
+    let mut params = item.split(';');
+    let value = params.next()?.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut quality = 1.0;
+    let mut media_params = Vec::new();
+    for param in params {
+        let (key, val) = param.split_once('=').unwrap_or((param, ""));
+        let (key, val) = (key.trim(), val.trim());
+        if key.eq_ignore_ascii_case("q") {
+            quality = val.parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+            break;
+        }
+        media_params.push(format!("{}={}", key, val));
+    }
+
+    let value = if media_params.is_empty() {
+        value.to_string()
+    } else {
+        format!("{};{}", value, media_params.join(";"))
+    };
+    Some(QualityItem { value, quality })
+}
+
+/// How well `range` matches `offer`, higher being more specific.
+type Matcher = fn(range: &str, offer: &str) -> Option<u32>;
+
+pub(crate) fn media_matches(range: &str, offer: &str) -> Option<u32> {
+    let split = |s: &str| {
+        let mut parts = s.split(';');
+        let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let params = parts.map(|p| p.trim().to_ascii_lowercase()).collect::<Vec<_>>();
+        (essence, params)
+    };
+    let (range, range_params) = split(range);
+    let (offer, offer_params) = split(offer);
+    let (range_type, range_sub) = range.split_once('/')?;
+    let (offer_type, offer_sub) = offer.split_once('/')?;
+
+    if range_type == "*" {
+        return (range_sub == "*").then_some(0);
+    }
+    if range_type != offer_type {
+        return None;
+    }
+    if range_sub == "*" {
+        return Some(1);
+    }
+    if range_sub != offer_sub || !range_params.iter().all(|p| offer_params.contains(p)) {
+        return None;
+    }
+    Some(2 + range_params.len() as u32)
+}
+
+pub(crate) fn language_matches(range: &str, offer: &str) -> Option<u32> {
+    if range == "*" {
+        return Some(0);
+    }
+    let prefix_of = offer.len() > range.len()
+        && offer.as_bytes()[range.len()] == b'-'
+        && offer[..range.len()].eq_ignore_ascii_case(range);
+    (prefix_of || offer.eq_ignore_ascii_case(range)).then_some(range.len() as u32)
+}
+
+pub(crate) fn token_matches(range: &str, offer: &str) -> Option<u32> {
+    if range == "*" {
+        Some(0)
+    } else {
+        offer.eq_ignore_ascii_case(range).then_some(1)
+    }
+}
+
+/// Picks the acceptable offer with the highest quality, preferring earlier
+/// offers on ties. Each offer is rated by the most specific range matching
+/// it; offers nothing matches get `unmatched`.
+pub(crate) fn negotiate<'a>(
+    items: &[QualityItem],
+    offers: &[&'a str],
+    matches: Matcher,
+    unmatched: impl Fn(&str) -> f32,
+) -> Option<&'a str> {
+    if items.is_empty() {
+        return offers.first().copied();
+    }
+
+    let mut best: Option<(&str, f32)> = None;
+    for offer in offers {
+        let quality = items.iter()
+            .filter_map(|item| matches(&item.value, offer).map(|rank| (rank, item.quality)))
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, quality)| quality)
+            .unwrap_or_else(|| unmatched(offer));
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((offer, quality));
+        }
+    }
+    best.map(|(offer, _)| offer)
+}
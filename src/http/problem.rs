@@ -0,0 +1,127 @@
+use std::fmt;
+
+use http::{HeaderValue, StatusCode, header};
+use serde::{Serialize, Serializer, ser::SerializeMap};
+use serde_json::{Map, Value};
+
+use crate::error::ResponseError;
+use super::response::Response;
+
+/// An RFC 9457 problem details object, served as
+/// `application/problem+json`.
+///
+/// It can be returned from handlers or raised with `?`; either way it travels
+/// up as an error and is rendered when it reaches the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    type_uri: Option<String>,
+    title: Option<String>,
+    status: StatusCode,
+    detail: Option<String>,
+    instance: Option<String>,
+    extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// A problem with the default `about:blank` type, titled after the
+    /// status reason.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            type_uri: None,
+            title: status.canonical_reason().map(str::to_string),
+            status,
+            detail: None,
+            instance: None,
+            extensions: Map::new(),
+        }
+    }
+
+    pub fn type_uri(mut self, type_uri: impl Into<String>) -> Self {
+        self.type_uri = Some(type_uri.into());
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Adds an extension member. Names of the standard members are ignored.
+    pub fn extension(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        let name = name.into();
+        if !matches!(name.as_str(), "type" | "title" | "status" | "detail" | "instance") {
+            self.extensions.insert(name, value.into());
+        }
+        self
+    }
+
+    pub fn status(&self) -> StatusCode { self.status }
+    pub fn extensions(&self) -> &Map<String, Value> { &self.extensions }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    pub fn to_response(&self) -> Response {
+        let mut res = Response::new(self.status);
+        res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        res.set_body(&serde_json::to_vec(self).unwrap_or_default()[..]);
+        res
+    }
+}
+
+/// Members are written in the order RFC 9457 lists them, then extensions.
+impl Serialize for Problem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(type_uri) = &self.type_uri {
+            map.serialize_entry("type", type_uri)?;
+        }
+        if let Some(title) = &self.title {
+            map.serialize_entry("title", title)?;
+        }
+        map.serialize_entry("status", &self.status.as_u16())?;
+        if let Some(detail) = &self.detail {
+            map.serialize_entry("detail", detail)?;
+        }
+        if let Some(instance) = &self.instance {
+            map.serialize_entry("instance", instance)?;
+        }
+        for (name, value) in &self.extensions {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, "{}: {}", title, detail),
+            (Some(text), None) | (None, Some(text)) => f.write_str(text),
+            (None, None) => write!(f, "{}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for Problem {}
+
+impl ResponseError for Problem {
+    fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    fn problem(&self) -> Problem {
+        self.clone()
+    }
+}
@@ -1,16 +1,35 @@
+use std::cell::OnceCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use bytes::BytesMut;
 use http::{Method, Uri, Version, HeaderMap};
 use serde_urlencoded;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
 
+use crate::error::Error;
+use super::body::BodyStream;
+use super::multipart::{self, Multipart};
+use super::cookie::parse_cookie_header;
+use super::cookie_jar::CookieJar;
+use super::cookie_keys::CookieKeys;
+use super::extensions::Extensions;
+use super::connection::ConnectionInfo;
+use super::query::Query;
+use super::negotiate::{self, QualityItem};
+use crate::session::Session;
+
 pub struct Request {
     method: Method,
     uri: Uri,
     version: Version,
     headers: HeaderMap,
     body: BytesMut,
+    body_stream: Option<BodyStream>,
     path_params: HashMap<String, String>,
+    host_params: HashMap<String, String>,
+    extensions: Extensions,
+    query: OnceCell<std::result::Result<Query, String>>,
 }
 
 impl Request {
@@ -21,14 +40,14 @@ impl Request {
             version: Version::HTTP_11,
             headers: HeaderMap::new(),
             body: BytesMut::new(),
+            body_stream: None,
             path_params: HashMap::new(),
+            host_params: HashMap::new(),
+            extensions: Extensions::new(),
+            query: OnceCell::new(),
         }
     }
 
-    pub fn default() -> Self {
-        Self::new(Method::GET, Uri::from_static("/"))
-    }
-
     pub fn method(&self) -> &Method { &self.method }
     pub fn uri(&self) -> &Uri { &self.uri }
     pub fn version(&self) -> Version { self.version }
@@ -40,17 +59,174 @@ impl Request {
 
     pub fn set_body(&mut self, body: impl Into<BytesMut>) {
         self.body = body.into();
+        self.body_stream = None;
     }
 
     pub fn set_body_str(&mut self, body: &str) -> &mut Self {
         self.body = BytesMut::from(body.as_bytes());
+        self.body_stream = None;
         self
     }
 
+    /// The body as a stream. A body left on the connection by
+    /// `BodyMode::Stream` is read as the stream is polled; otherwise the
+    /// buffered body is handed over and `body()` is empty afterwards.
+    pub fn take_body_stream(&mut self) -> BodyStream {
+        match self.body_stream.take() {
+            Some(stream) => stream,
+            None => BodyStream::from_bytes(std::mem::take(&mut self.body).freeze()),
+        }
+    }
+
+    pub(crate) fn set_body_stream(&mut self, stream: BodyStream) {
+        self.body = BytesMut::new();
+        self.body_stream = Some(stream);
+    }
+
     pub fn body_str(&self) -> Option<&str> {
         std::str::from_utf8(self.body.as_ref()).ok()
     }
 
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(http::header::CONTENT_TYPE).and_then(|v| v.to_str().ok())
+    }
+
+    fn expect_media_type(&self, expected: &str) -> crate::error::Result<&str> {
+        let content_type = self.content_type().unwrap_or("");
+        let media_type = content_type.split(';').next().unwrap_or("").trim();
+        if media_type.eq_ignore_ascii_case(expected) {
+            Ok(content_type)
+        } else {
+            Err(Error::UnsupportedMediaType(format!("expected {}", expected)))
+        }
+    }
+
+    /// `Accept` entries, best first.
+    pub fn accept(&self) -> Vec<QualityItem> {
+        negotiate::parse_header(&self.headers, http::header::ACCEPT)
+    }
+
+    pub fn accept_language(&self) -> Vec<QualityItem> {
+        negotiate::parse_header(&self.headers, http::header::ACCEPT_LANGUAGE)
+    }
+
+    pub fn accept_encoding(&self) -> Vec<QualityItem> {
+        negotiate::parse_header(&self.headers, http::header::ACCEPT_ENCODING)
+    }
+
+    pub fn accept_charset(&self) -> Vec<QualityItem> {
+        negotiate::parse_header(&self.headers, http::header::ACCEPT_CHARSET)
+    }
+
+    /// Picks the media type from `offered` the client prefers, honouring
+    /// q-values and `type/*` or `*/*` ranges. Ties go to the earlier offer,
+    /// and a request without `Accept` gets the first one.
+    pub fn negotiate<'a>(&self, offered: &[&'a str]) -> crate::error::Result<&'a str> {
+        negotiate::negotiate(&self.accept(), offered, negotiate::media_matches, |_| 0.0)
+            .ok_or_else(|| Error::NotAcceptable(format!("expected one of {}", offered.join(", "))))
+    }
+
+    /// Like `negotiate`, for language tags; `en` also matches `en-US`.
+    pub fn negotiate_language<'a>(&self, offered: &[&'a str]) -> crate::error::Result<&'a str> {
+        negotiate::negotiate(&self.accept_language(), offered, negotiate::language_matches, |_| 0.0)
+            .ok_or_else(|| Error::NotAcceptable(format!("expected a language among {}", offered.join(", "))))
+    }
+
+    /// Like `negotiate`, for content codings. `identity` is acceptable
+    /// unless the client rules it out, and is chosen when offered to a
+    /// request without `Accept-Encoding`.
+    pub fn negotiate_encoding<'a>(&self, offered: &[&'a str]) -> crate::error::Result<&'a str> {
+        let is_identity = |offer: &str| offer.eq_ignore_ascii_case("identity");
+        let accepted = self.accept_encoding();
+        if accepted.is_empty()
+            && let Some(identity) = offered.iter().find(|offer| is_identity(offer))
+        {
+            return Ok(identity);
+        }
+        let identity = |offer: &str| if is_identity(offer) { 1.0 } else { 0.0 };
+        negotiate::negotiate(&accepted, offered, negotiate::token_matches, identity)
+            .ok_or_else(|| Error::NotAcceptable(format!("expected an encoding among {}", offered.join(", "))))
+    }
+
+    pub fn negotiate_charset<'a>(&self, offered: &[&'a str]) -> crate::error::Result<&'a str> {
+        negotiate::negotiate(&self.accept_charset(), offered, negotiate::token_matches, |_| 0.0)
+            .ok_or_else(|| Error::NotAcceptable(format!("expected a charset among {}", offered.join(", "))))
+    }
+
+    /// Deserializes an `application/x-www-form-urlencoded` body.
+    pub fn form<T: serde::de::DeserializeOwned>(&self) -> crate::error::Result<T> {
+        self.expect_media_type("application/x-www-form-urlencoded")?;
+        Ok(serde_urlencoded::from_bytes(&self.body)?)
+    }
+
+    /// Reads a `multipart/form-data` body part by part, taking it over as
+    /// with `take_body_stream`.
+    pub fn multipart(&mut self) -> crate::error::Result<Multipart> {
+        let content_type = self.expect_media_type("multipart/form-data")?;
+        let boundary = multipart::header_param(content_type, "boundary")
+            .filter(|b| !b.is_empty())
+            .ok_or_else(|| Error::BadRequest("Missing multipart boundary".into()))?;
+        Ok(Multipart::new(self.take_body_stream(), &boundary))
+    }
+
+    /// All cookies sent in `Cookie` headers, in the order received.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(parse_cookie_header)
+    }
+
+    pub fn cookie<T: AsRef<str>>(&self, name: T) -> Option<&str> {
+        self.cookies()
+            .find(|(n, _)| *n == name.as_ref())
+            .map(|(_, v)| v)
+    }
+
+    /// Reads a cookie written with `Response::add_signed_cookie`, ignoring
+    /// it if the signature does not match.
+    pub fn signed_cookie<T: AsRef<str>>(&self, name: T, keys: &CookieKeys) -> Option<String> {
+        let name = name.as_ref();
+        self.cookie(name).and_then(|value| keys.verify(name, value))
+    }
+
+    /// Reads a cookie written with `Response::add_private_cookie`, ignoring
+    /// it if it cannot be decrypted.
+    pub fn private_cookie<T: AsRef<str>>(&self, name: T, keys: &CookieKeys) -> Option<String> {
+        let name = name.as_ref();
+        self.cookie(name).and_then(|value| keys.decrypt(name, value))
+    }
+
+    /// The request's cookies together with the keys registered through
+    /// `App::cookie_keys`.
+    pub fn cookie_jar(&self) -> crate::error::Result<CookieJar> {
+        let keys = self.extensions.get::<Rc<CookieKeys>>()
+            .ok_or_else(|| Error::Internal("no cookie keys registered with the app".into()))?;
+        Ok(CookieJar::new(self, keys.clone()))
+    }
+
+    pub fn extensions(&self) -> &Extensions { &self.extensions }
+    pub fn extensions_mut(&mut self) -> &mut Extensions { &mut self.extensions }
+
+    /// Addresses of the connection, set by the server.
+    pub fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.extensions.get()
+    }
+
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.connection_info().map(|info| info.remote_addr())
+    }
+
+    /// The originating client, see `ConnectionInfo::client_ip`.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.connection_info().map(|info| info.client_ip())
+    }
+
+    /// The session loaded by a `SessionService` wrapping the handler.
+    pub fn session(&self) -> Option<&Session> {
+        self.extensions.get()
+    }
+
     pub fn path_param<T: AsRef<str>>(&self, key: T) -> Option<&str> {
         self.path_params.get(key.as_ref()).map(|s| s.as_str())
     }
@@ -69,21 +245,72 @@ impl Request {
         self
     }
 
+    /// The requested host without its port, taken from the URI authority
+    /// or else the `Host` header.
+    pub fn host(&self) -> Option<&str> {
+        let host = match self.uri.host() {
+            Some(host) => host,
+            None => {
+                let value = self.headers.get(http::header::HOST)?.to_str().ok()?;
+                match value.rfind(':') {
+                    Some(i) if !value[i..].contains(']') => &value[..i],
+                    _ => value,
+                }
+            }
+        };
+        Some(host).filter(|h| !h.is_empty())
+    }
+
+    pub fn host_param<T: AsRef<str>>(&self, key: T) -> Option<&str> {
+        self.host_params.get(key.as_ref()).map(|s| s.as_str())
+    }
+
+    pub fn host_params(&self) -> &HashMap<String, String> {
+        &self.host_params
+    }
+
+    pub fn set_host_params(&mut self, params: HashMap<String, String>) -> &mut Self {
+        self.host_params = params;
+        self
+    }
+
+    /// The parsed query string, decoded once and cached until the URI
+    /// changes.
+    pub fn parsed_query(&self) -> crate::error::Result<&Query> {
+        self.query
+            .get_or_init(|| Query::parse(self.uri.query().unwrap_or("")).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| Error::InvalidQuery(e.clone()))
+    }
+
+    /// Deserializes the query string into `T`. Repeated keys fill `Vec`
+    /// fields, so `?tag=a&tag=b` gives `tag: vec!["a", "b"]`.
+    pub fn query_as<T: serde::de::DeserializeOwned>(&self) -> crate::error::Result<T> {
+        self.parsed_query()?.deserialize()
+    }
+
+    /// The last value of `key`; see `query_all` for repeated keys.
     pub fn query<T: AsRef<str>>(&self, key: T) -> Option<String> {
-        self.uri.query().and_then(|q| {
-            let params = serde_urlencoded::from_str::<HashMap<String, String>>(q).ok()?;
-            params.get(key.as_ref()).map(|s| s.to_owned())
-        })
+        self.parsed_query().ok()?.get(key.as_ref()).map(str::to_owned)
+    }
+
+    /// Every value of `key`, in order of appearance.
+    pub fn query_all<T: AsRef<str>>(&self, key: T) -> Vec<&str> {
+        match self.parsed_query() {
+            Ok(query) => query.get_all(key.as_ref()).collect(),
+            Err(_) => Vec::new(),
+        }
     }
 
     pub fn query_params(&self) -> Option<HashMap<String, String>> {
-        self.uri.query().and_then(|q| {
-            serde_urlencoded::from_str::<HashMap<String, String>>(q).ok()
-        })
+        self.uri.query()?;
+        let query = self.parsed_query().ok()?;
+        Some(query.pairs().iter().cloned().collect())
     }
 
     pub fn with_uri(&mut self, uri: Uri) -> &mut Self {
         self.uri = uri;
+        self.query = OnceCell::new();
         self
     }
 
@@ -112,10 +339,17 @@ impl Request {
             .expect("valid URI parts");
         
         self.uri = new_uri;
+        self.query = OnceCell::new();
         Ok(self)
     }
 }
 
+impl Default for Request {
+    fn default() -> Self {
+        Self::new(Method::GET, Uri::from_static("/"))
+    }
+}
+
 impl Clone for Request {
     fn clone(&self) -> Self {
         Self {
@@ -124,7 +358,11 @@ impl Clone for Request {
             version: self.version,
             headers: self.headers.clone(),
             body: self.body.clone(),
+            body_stream: None,
             path_params: self.path_params.clone(),
+            host_params: self.host_params.clone(),
+            extensions: self.extensions.clone(),
+            query: self.query.clone(),
         }
     }
 }
\ No newline at end of file
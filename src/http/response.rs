@@ -1,11 +1,20 @@
 use bytes::BytesMut;
-use http::{StatusCode, HeaderMap};
+use http::{StatusCode, HeaderMap, HeaderName, HeaderValue, header};
+
+use crate::error::Result;
+use super::cookie::Cookie;
+use super::cookie_jar::CookieJar;
+use super::cookie_keys::CookieKeys;
+use super::extensions::Extensions;
+use super::file_region::{FilePart, FileRegion};
 
 #[derive(Clone)]
 pub struct Response {
     status: StatusCode,
     headers: HeaderMap,
     body: BytesMut,
+    file: Vec<FilePart>,
+    extensions: Extensions,
 }
 
 impl Response {
@@ -14,28 +23,219 @@ impl Response {
             status,
             headers: HeaderMap::new(),
             body: BytesMut::new(),
+            file: Vec::new(),
+            extensions: Extensions::new(),
         }
     }
 
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder::new()
+    }
+
     pub fn ok() -> Self { Self::new(StatusCode::OK) }
     pub fn not_found() -> Self { Self::new(StatusCode::NOT_FOUND) }
+    pub fn no_content() -> Self { Self::new(StatusCode::NO_CONTENT) }
+
+    /// `201 Created` pointing at the new resource.
+    pub fn created(location: &str) -> Result<Self> {
+        Self::with_location(StatusCode::CREATED, location)
+    }
+
+    /// `301 Moved Permanently`; clients may follow it with a `GET`.
+    pub fn moved_permanently(location: &str) -> Result<Self> {
+        Self::with_location(StatusCode::MOVED_PERMANENTLY, location)
+    }
+
+    /// `308 Permanent Redirect`; the method and body are kept.
+    pub fn permanent_redirect(location: &str) -> Result<Self> {
+        Self::with_location(StatusCode::PERMANENT_REDIRECT, location)
+    }
+
+    /// `307 Temporary Redirect`; the method and body are kept.
+    pub fn temporary_redirect(location: &str) -> Result<Self> {
+        Self::with_location(StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    /// `303 See Other`, which the client follows with a `GET`, as after a
+    /// form submission.
+    pub fn see_other(location: &str) -> Result<Self> {
+        Self::with_location(StatusCode::SEE_OTHER, location)
+    }
+
+    fn with_location(status: StatusCode, location: &str) -> Result<Self> {
+        Self::builder().status(status).header(header::LOCATION, location).build()
+    }
+
+    /// A `text/plain; charset=utf-8` response.
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::with_body("text/plain; charset=utf-8", body.into())
+    }
+
+    /// A `text/html; charset=utf-8` response.
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::with_body("text/html; charset=utf-8", body.into())
+    }
+
+    fn with_body(content_type: &'static str, body: String) -> Self {
+        let mut res = Self::ok();
+        res.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        res.body = BytesMut::from(body.as_bytes());
+        res
+    }
+
     pub fn status(&self) -> StatusCode { self.status }
     pub fn set_status(&mut self, status: StatusCode) { self.status = status; }
     pub fn headers(&self) -> &HeaderMap { &self.headers }
     pub fn headers_mut(&mut self) -> &mut HeaderMap { &mut self.headers }
     pub fn body(&self) -> &BytesMut { &self.body }
     pub fn body_mut(&mut self) -> &mut BytesMut { &mut self.body }
+    pub fn extensions(&self) -> &Extensions { &self.extensions }
+    pub fn extensions_mut(&mut self) -> &mut Extensions { &mut self.extensions }
     
     pub fn set_body(&mut self, body: impl Into<BytesMut>) {
         self.body = body.into();
+        self.file.clear();
     }
     
     pub fn set_body_str(&mut self, body: &str) -> &mut Self {
         self.body = BytesMut::from(body.as_bytes());
+        self.file.clear();
         self
     }
     
+    /// Makes `region` the body; the server sends it straight from the file.
+    pub fn set_file_body(&mut self, region: FileRegion) {
+        self.set_file_parts(vec![FilePart::Region(region)]);
+    }
+
+    /// Makes `parts` the body, sent in order with regions taken straight
+    /// from their files.
+    pub fn set_file_parts(&mut self, parts: Vec<FilePart>) {
+        self.body.clear();
+        self.file = parts;
+    }
+
+    /// The file region making up the whole body, if that is what it is.
+    pub fn file_body(&self) -> Option<&FileRegion> {
+        match self.file.as_slice() {
+            [FilePart::Region(region)] => Some(region),
+            _ => None,
+        }
+    }
+
+    /// The parts of a body set with `set_file_body` or `set_file_parts`.
+    pub fn file_parts(&self) -> &[FilePart] { &self.file }
+
     pub fn body_str(&self) -> Option<&str> {
         std::str::from_utf8(self.body.as_ref()).ok()
     }
-}
\ No newline at end of file
+
+    /// Appends a `Set-Cookie` header, keeping any cookies already set.
+    /// Fails with `Error::InvalidCookie` if the cookie does not pass
+    /// [`Cookie::validate`].
+    pub fn add_cookie(&mut self, cookie: Cookie) -> Result<&mut Self> {
+        cookie.validate()?;
+        let value = HeaderValue::try_from(cookie.to_string()).map_err(http::Error::from)?;
+        self.headers.append(header::SET_COOKIE, value);
+        Ok(self)
+    }
+
+    /// Sends the cookies added or removed through `jar`.
+    pub fn add_cookies(&mut self, jar: &CookieJar) -> Result<&mut Self> {
+        for cookie in jar.delta() {
+            self.add_cookie(cookie.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Adds a cookie whose value is readable by the client but tamper-proof.
+    pub fn add_signed_cookie(&mut self, cookie: Cookie, keys: &CookieKeys) -> Result<&mut Self> {
+        let value = keys.sign(cookie.name(), cookie.value());
+        self.add_cookie(cookie.with_value(value))
+    }
+
+    /// Adds a cookie whose value is encrypted and authenticated.
+    pub fn add_private_cookie(&mut self, cookie: Cookie, keys: &CookieKeys) -> Result<&mut Self> {
+        let value = keys.encrypt(cookie.name(), cookie.value());
+        self.add_cookie(cookie.with_value(value))
+    }
+}
+
+/// Fluent construction of a [`Response`].
+///
+/// Invalid header names or values are reported when the response is built
+/// rather than at each step.
+pub struct ResponseBuilder {
+    inner: Result<Response>,
+}
+
+impl ResponseBuilder {
+    pub fn new() -> Self {
+        Self { inner: Ok(Response::ok()) }
+    }
+
+    pub fn status(self, status: StatusCode) -> Self {
+        self.map(|res| {
+            res.status = status;
+            Ok(())
+        })
+    }
+
+    /// Appends a header, keeping earlier values of the same name.
+    pub fn header<K, V>(self, name: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.map(|res| {
+            let name = HeaderName::try_from(name).map_err(Into::into)?;
+            let value = HeaderValue::try_from(value).map_err(Into::into)?;
+            res.headers.append(name, value);
+            Ok(())
+        })
+    }
+
+    pub fn content_type(self, content_type: &str) -> Self {
+        self.map(|res| {
+            let value = HeaderValue::try_from(content_type).map_err(http::Error::from)?;
+            res.headers.insert(header::CONTENT_TYPE, value);
+            Ok(())
+        })
+    }
+
+    pub fn cookie(self, cookie: Cookie) -> Self {
+        self.map(|res| res.add_cookie(cookie).map(|_| ()))
+    }
+
+    pub fn extension<T: Clone + 'static>(self, value: T) -> Self {
+        self.map(|res| {
+            res.extensions.insert(value);
+            Ok(())
+        })
+    }
+
+    pub fn body(self, body: impl Into<BytesMut>) -> Result<Response> {
+        let mut res = self.inner?;
+        res.body = body.into();
+        Ok(res)
+    }
+
+    /// Finishes the response with an empty body.
+    pub fn build(self) -> Result<Response> {
+        self.inner
+    }
+
+    fn map(self, f: impl FnOnce(&mut Response) -> Result<()>) -> Self {
+        Self {
+            inner: self.inner.and_then(|mut res| f(&mut res).map(|_| res)),
+        }
+    }
+}
+
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -5,12 +5,14 @@ pub mod http;
 pub mod error;
 pub mod server;
 pub mod service;
+pub mod session;
+pub mod compression;
 
 pub mod prelude {
     pub use crate::app::App;
     pub use crate::service::{Service, Router};
-    pub use crate::http::{Request, Response};
-    pub use crate::error::{Error, Result};
+    pub use crate::http::{Request, Response, IntoResponse, Json, Problem};
+    pub use crate::error::{Error, Result, ResponseError, HttpError};
     pub use crate::server::Server;
     pub use http::{Method, StatusCode};
 }
@@ -1,39 +1,92 @@
+use std::cell::{Cell, RefCell};
 use std::io::Result as IoResult;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use monoio::buf::{IoBuf, IoBufMut, IoVecBuf};
 use monoio::net::{TcpListener, TcpStream};
-use monoio::io::{AsyncReadRent, AsyncWriteRent};
-use bytes::{BytesMut, BufMut};
+use monoio::io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt};
+use bytes::BytesMut;
 use httparse::{Request as HttpParseRequest, EMPTY_HEADER, Status};
-use http::{Method, Uri, Version, HeaderName, HeaderValue};
+use http::{Method, StatusCode, Uri, Version, HeaderName, HeaderValue, header::{CONNECTION, CONTENT_LENGTH, DATE, EXPECT, SERVER}};
 
 use crate::{
     app::App,
-    error::Result,
-    http::{Request, Response},
+    error::{Error, Result},
+    http::{BodyMode, BodyStream, FilePart, Incoming, Request, Response, ConnectionInfo, TrustedProxies},
 };
 
 const MAX_HEADERS: usize = 100;
 const BUFFER_SIZE: usize = 1048576;
+const MAX_BODY_SIZE: usize = 1048576;
+/// Unread body bytes discarded after an early response before the
+/// connection is closed anyway.
+const MAX_DRAIN_SIZE: u64 = 1048576;
+/// How long discarding an unread body may hold up the server.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub struct Server {
     addr: String,
+    trusted_proxies: TrustedProxies,
+    expose_error_details: bool,
+    server_header: Option<HeaderValue>,
+    max_body_size: usize,
 }
 
 impl Server {
     pub fn bind(addr: impl Into<String>) -> Self {
         Self {
             addr: addr.into(),
+            trusted_proxies: TrustedProxies::new(),
+            expose_error_details: false,
+            server_header: None,
+            max_body_size: MAX_BODY_SIZE,
         }
     }
-    
+
+    /// Proxies whose `Forwarded` / `X-Forwarded-For` headers are used to
+    /// resolve `ConnectionInfo::client_ip`.
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = proxies;
+        self
+    }
+
+    /// Includes the messages of server errors in rendered problem
+    /// responses. Meant for development; off by default.
+    pub fn expose_error_details(mut self, expose: bool) -> Self {
+        self.expose_error_details = expose;
+        self
+    }
+
+    /// `Server` header added to responses that don't set their own.
+    pub fn server_header(mut self, value: HeaderValue) -> Self {
+        self.server_header = Some(value);
+        self
+    }
+
+    /// Limit for bodies buffered into `Request::body`, 1 MiB by default.
+    /// Longer bodies are answered with `413 Payload Too Large`, before they
+    /// are read when `Content-Length` declares them. Bodies a service
+    /// streams (see `Service::before_body`) are not limited here.
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// Serves `app` on the bound address. The runtime must have its timer
+    /// enabled (`RuntimeBuilder::enable_timer`): it bounds how long the
+    /// unread body of a rejected request is drained.
     pub async fn serve<State, S: crate::service::Service<State>>(self, app: &App<State, S>) -> Result<()> {
         let listener = TcpListener::bind(self.addr.clone())?;
         tracing::info!("Server running on {}", self.addr);
         
+        let mut next_id = 0;
         loop {
             let (stream, addr) = listener.accept().await?;
             tracing::debug!("Accepted connection from {}", addr);
-            
-            match handle_connection(stream, app).await {
+
+            next_id += 1;
+            let info = ConnectionInfo::new(next_id, addr, stream.local_addr()?);
+            match handle_connection(stream, info, &self, app).await {
                 Ok(_) => (),
                 Err(e) => tracing::error!("Error handling connection: {}", e),
             }
@@ -41,45 +94,174 @@ impl Server {
     }
 }
 
-async fn handle_connection<State, S: crate::service::Service<State>>(mut stream: TcpStream, app: &App<State, S>) -> Result<()> {
-    let request = read_http_request(&mut stream).await?;
-    let response = app.handle(request).await?;
-    write_response(stream, response).await?;
+async fn handle_connection<State, S: crate::service::Service<State>>(
+    mut stream: TcpStream,
+    info: ConnectionInfo,
+    server: &Server,
+    app: &App<State, S>,
+) -> Result<()> {
+    let (mut request, received) = read_head(&mut stream).await?;
+    let client_ip = server.trusted_proxies.client_ip(info.remote_addr().ip(), request.headers());
+    request.extensions_mut().insert(info.with_client_ip(client_ip));
+
+    let length = content_length(&request, received.len());
+    let incoming = Incoming::new(stream, received, *length.as_ref().unwrap_or(&0));
+    let result = match length {
+        Ok(length) => match prepare_body(&incoming, &mut request, length, server, app).await {
+            Ok(()) => app.handle(request).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    let mut response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            if e.status().is_server_error() {
+                tracing::error!("Error handling request: {}", error_chain(&e));
+            } else {
+                tracing::debug!("Request failed: {}", error_chain(&e));
+            }
+            e.render(server.expose_error_details)
+        }
+    };
+    let Some(mut stream) = incoming.take_stream() else {
+        tracing::debug!("Connection abandoned while reading the request body");
+        return Ok(());
+    };
+
+    // A response sent before the whole body was read ends the connection.
+    let unread = incoming.unread_on_socket();
+    if unread > 0 {
+        response.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+    }
+    write_response(&mut stream, response, server).await?;
+
+    // A client still waiting for `100 Continue` never sends the body.
+    if unread > 0 && !incoming.continue_pending() {
+        match monoio::time::timeout(DRAIN_TIMEOUT, discard_body(&mut stream, unread)).await {
+            Ok(result) => result?,
+            Err(_) => tracing::debug!("Gave up discarding an unread request body"),
+        }
+    }
     Ok(())
 }
 
-async fn read_http_request(stream: &mut TcpStream) -> Result<Request> {
+/// Closes our side and reads what is left of a body the response was sent
+/// without, so closing the socket does not reset the connection before the
+/// client has read the response.
+async fn discard_body(stream: &mut TcpStream, unread: u64) -> IoResult<()> {
+    stream.shutdown().await?;
+    let mut discarded = 0;
+    let mut buf = Vec::with_capacity(64 * 1024);
+    while discarded < unread.min(MAX_DRAIN_SIZE) {
+        let (result, read) = stream.read(buf).await;
+        buf = read;
+        match result? {
+            0 => break,
+            n => discarded += n as u64,
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// Formats an error followed by its causes, `outer: inner: root`.
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Reads and parses the request head. Returns the request without a body
+/// and whatever bytes arrived after the head.
+async fn read_head(stream: &mut TcpStream) -> Result<(Request, BytesMut)> {
     let mut buffer = BytesMut::with_capacity(BUFFER_SIZE);
-    buffer.resize(BUFFER_SIZE, 0);
-    
-    let (result, buf) = stream.read(buffer).await;
-    buffer = buf;
-    let n = result?;
-    buffer.truncate(n);
-    
-    let mut headers = [EMPTY_HEADER; MAX_HEADERS];
-    let mut req = HttpParseRequest::new(&mut headers);
-    let status = req.parse(&buffer)?;
-    
-    match status {
-        Status::Complete(size) => convert_request(req, &buffer[size..]),
-        Status::Partial => {
+    loop {
+        let len = buffer.len();
+        let (result, slice) = stream.read(buffer.slice_mut(len..)).await;
+        buffer = slice.into_inner();
+        if result? == 0 {
             tracing::warn!("Incomplete HTTP request received");
-            Err(crate::error::Error::BadRequest("Incomplete HTTP request".into()))
+            return Err(Error::BadRequest("Incomplete HTTP request".into()));
+        }
+
+        let mut headers = [EMPTY_HEADER; MAX_HEADERS];
+        let mut req = HttpParseRequest::new(&mut headers);
+        match req.parse(&buffer)? {
+            Status::Complete(size) => {
+                let request = convert_request(req)?;
+                return Ok((request, buffer.split_off(size)));
+            }
+            Status::Partial if buffer.len() == buffer.capacity() => {
+                return Err(Error::BadRequest("Request head too large".into()));
+            }
+            Status::Partial => (),
+        }
+    }
+}
+
+/// The body length declared by `Content-Length`. Without one, the body is
+/// whatever arrived along with the head.
+fn content_length(request: &Request, received: usize) -> Result<u64> {
+    match request.headers().get(CONTENT_LENGTH) {
+        Some(value) => value.to_str().ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .ok_or_else(|| Error::BadRequest("Invalid Content-Length".into())),
+        None => Ok(received as u64),
+    }
+}
+
+/// Reads the body into `request`, or leaves it on the connection when the
+/// service asks to stream it. `Service::before_body` runs first and may
+/// reject the request before any of the body is read; otherwise
+/// `Expect: 100-continue` is answered right before the body is first read.
+async fn prepare_body<State, S: crate::service::Service<State>>(
+    incoming: &Rc<Incoming>,
+    request: &mut Request,
+    length: u64,
+    server: &Server,
+    app: &App<State, S>,
+) -> Result<()> {
+    if let Some(expect) = request.headers().get(EXPECT) {
+        if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue") {
+            return Err(Error::ExpectationFailed(format!("unsupported expectation {:?}", expect)));
+        }
+        // HTTP/1.0 clients don't know interim responses.
+        if request.version() == Version::HTTP_11 {
+            incoming.expect_continue();
         }
     }
+
+    match app.before_body(request).await? {
+        BodyMode::Buffer => {
+            if length > server.max_body_size as u64 {
+                return Err(Error::PayloadTooLarge(format!(
+                    "body of {} bytes exceeds the limit of {} bytes", length, server.max_body_size
+                )));
+            }
+            let body = BodyStream::connection(incoming.clone()).collect(server.max_body_size).await?;
+            request.set_body(body);
+        }
+        BodyMode::Stream => request.set_body_stream(BodyStream::connection(incoming.clone())),
+    }
+    Ok(())
 }
 
-fn convert_request(req: HttpParseRequest, body: &[u8]) -> Result<Request> {
+fn convert_request(req: HttpParseRequest) -> Result<Request> {
     let method = req.method
-        .ok_or_else(|| crate::error::Error::BadRequest("Missing method".into()))
+        .ok_or_else(|| Error::BadRequest("Missing method".into()))
         .and_then(|m| Method::from_bytes(m.as_bytes())
-            .map_err(|_| crate::error::Error::BadRequest("Invalid method".into())))?;
+            .map_err(|_| Error::BadRequest("Invalid method".into())))?;
     
     let uri = req.path
-        .ok_or_else(|| crate::error::Error::BadRequest("Missing URI".into()))
+        .ok_or_else(|| Error::BadRequest("Missing URI".into()))
         .and_then(|p| Uri::try_from(p)
-            .map_err(|_| crate::error::Error::BadRequest("Invalid URI".into())))?;
+            .map_err(|_| Error::BadRequest("Invalid URI".into())))?;
     
     let version = match req.version {
         Some(0) => Version::HTTP_10,
@@ -90,51 +272,194 @@ fn convert_request(req: HttpParseRequest, body: &[u8]) -> Result<Request> {
     let mut request = Request::new(method, uri);
     request.set_version(version);
     
-    for header in req.headers.iter().filter(|h| !h.name.is_empty() && !h.value.is_empty()) {
+    for header in req.headers.iter().filter(|h| !h.name.is_empty()) {
         let name = HeaderName::from_bytes(header.name.as_bytes())
-            .map_err(|_| crate::error::Error::BadRequest(
+            .map_err(|_| Error::BadRequest(
                 format!("Invalid header name: {}", header.name)
             ))?;
             
         let value = HeaderValue::from_bytes(header.value)
-            .map_err(|_| crate::error::Error::BadRequest(
+            .map_err(|_| Error::BadRequest(
                 format!("Invalid header value for: {}", header.name)
             ))?;
             
-        request.headers_mut().insert(name, value);
+        request.headers_mut().append(name, value);
     }
     
-    request.set_body(BytesMut::from(body));
-    
     Ok(request)
 }
 
-async fn write_response(mut stream: TcpStream, response: Response) -> IoResult<()> {
+async fn write_response(stream: &mut TcpStream, mut response: Response, server: &Server) -> IoResult<()> {
+    let mut head = HEAD_BUFFER.take();
+    head.clear();
+    let body = std::mem::take(response.body_mut());
+    let len = match response.file_parts() {
+        [] => body.len() as u64,
+        parts => parts.iter().map(FilePart::len).sum(),
+    };
+    encode_head(&mut head, &response, len, server.server_header.as_ref());
+
+    let (result, head) = write_head_and_body(stream, head, body).await;
+    if head.capacity() <= MAX_REUSED_HEAD_CAPACITY {
+        HEAD_BUFFER.set(head);
+    }
+    result?;
+
+    for part in response.file_parts() {
+        match part {
+            FilePart::Bytes(bytes) => {
+                let (result, _) = stream.write_all(bytes.clone()).await;
+                result?;
+            }
+            FilePart::Region(region) => region.write_to(stream).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the head and body with one `writev`, finishing with plain writes
+/// after a short write. Returns the head buffer.
+///
+/// monoio's `write_vectored_all` is not used: in monoio 0.2.4 its
+/// `IoVecMeta::consume` never moves `iov_base` forward, so it resends data
+/// after a short write.
+async fn write_head_and_body(stream: &mut TcpStream, head: Vec<u8>, body: BytesMut) -> (IoResult<()>, Vec<u8>) {
+    let (result, HeadAndBody { mut head, body, .. }) = stream.writev(HeadAndBody::new(head, body)).await;
+    let mut written = match result {
+        Ok(n) => n,
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => 0,
+        Err(e) => return (Err(e), head),
+    };
+
+    if written < head.len() {
+        let (result, rest) = stream.write_all(head.slice(written..)).await;
+        head = rest.into_inner();
+        if let Err(e) = result {
+            return (Err(e), head);
+        }
+        written = 0;
+    } else {
+        written -= head.len();
+    }
+    if written < body.len() {
+        let (result, _) = stream.write_all(body.slice(written..)).await;
+        if let Err(e) = result {
+            return (Err(e), head);
+        }
+    }
+    (Ok(()), head)
+}
+
+/// The response head and body as the two entries of one `writev`, so the
+/// body is written from its own buffer however it was built.
+struct HeadAndBody {
+    head: Vec<u8>,
+    body: BytesMut,
+    iovecs: Vec<libc::iovec>,
+}
+
+impl HeadAndBody {
+    fn new(head: Vec<u8>, body: BytesMut) -> Self {
+        // Both buffers live on the heap, so the pointers stay valid when
+        // `Self` is moved into the runtime.
+        let iovecs = [&head[..], &body[..]]
+            .iter()
+            .map(|part| libc::iovec { iov_base: part.as_ptr() as *mut _, iov_len: part.len() })
+            .collect();
+        Self { head, body, iovecs }
+    }
+}
+
+unsafe impl IoVecBuf for HeadAndBody {
+    fn read_iovec_ptr(&self) -> *const libc::iovec {
+        self.iovecs.as_ptr()
+    }
+
+    fn read_iovec_len(&self) -> usize {
+        self.iovecs.len()
+    }
+}
+
+/// Head buffers that grew beyond this are dropped rather than kept.
+const MAX_REUSED_HEAD_CAPACITY: usize = 16 * 1024;
+
+thread_local! {
+    /// Response heads are serialized into this buffer, which keeps its
+    /// capacity between responses on the same thread. Reuse is best-effort:
+    /// the buffer is taken for the duration of a write, so a response
+    /// written meanwhile starts with a fresh one.
+    static HEAD_BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+
+    static CACHED_DATE: RefCell<CachedDate> = const { RefCell::new(CachedDate { secs: 0, text: String::new() }) };
+}
+
+/// The `Date` header value, formatted at most once per second.
+struct CachedDate {
+    secs: u64,
+    text: String,
+}
+
+impl CachedDate {
+    fn now(&mut self) -> &[u8] {
+        let now = SystemTime::now();
+        let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if secs != self.secs || self.text.is_empty() {
+            self.secs = secs;
+            self.text = httpdate::fmt_http_date(now);
+        }
+        self.text.as_bytes()
+    }
+}
+
+fn encode_head(buf: &mut Vec<u8>, response: &Response, body_len: u64, server_header: Option<&HeaderValue>) {
     let status = response.status();
+    buf.extend_from_slice(b"HTTP/1.1 ");
+    buf.extend_from_slice(status.as_str().as_bytes());
+    buf.push(b' ');
+    buf.extend_from_slice(status.canonical_reason().unwrap_or("").as_bytes());
+    buf.extend_from_slice(b"\r\n");
+
     let headers = response.headers();
-    let body = response.body();
-    
-    let mut res = BytesMut::new();
-    
-    res.put_slice(format!(
-        "HTTP/1.1 {} {}\r\n", 
-        status.as_u16(), 
-        status.canonical_reason().unwrap_or("")
-    ).as_bytes());
-    
     for (name, value) in headers.iter() {
-        if let Ok(value_str) = value.to_str() {
-            res.put_slice(format!("{}: {}\r\n", name, value_str).as_bytes());
-        }
+        buf.extend_from_slice(name.as_str().as_bytes());
+        buf.extend_from_slice(b": ");
+        buf.extend_from_slice(value.as_bytes());
+        buf.extend_from_slice(b"\r\n");
     }
-    
-    if !headers.contains_key("content-length") {
-        res.put_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+
+    if !headers.contains_key(DATE) {
+        buf.extend_from_slice(b"Date: ");
+        CACHED_DATE.with_borrow_mut(|date| buf.extend_from_slice(date.now()));
+        buf.extend_from_slice(b"\r\n");
     }
-    
-    res.put_slice(b"\r\n");
-    res.put_slice(body.as_ref());
-    
-    let (result, _) = stream.write(res).await;
-    result.map(|_| ())
-}
\ No newline at end of file
+    if let Some(server) = server_header
+        && !headers.contains_key(SERVER)
+    {
+        buf.extend_from_slice(b"Server: ");
+        buf.extend_from_slice(server.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+    // 1xx and 204 must not carry one, and on a 304 it would announce the
+    // length of the representation rather than of the (empty) body.
+    let bodiless = status.is_informational() || status == StatusCode::NO_CONTENT || status == StatusCode::NOT_MODIFIED;
+    if !bodiless && !headers.contains_key(CONTENT_LENGTH) {
+        buf.extend_from_slice(b"Content-Length: ");
+        encode_decimal(buf, body_len);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn encode_decimal(buf: &mut Vec<u8>, mut n: u64) {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    buf.extend_from_slice(&digits[i..]);
+}
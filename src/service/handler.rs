@@ -1,55 +1,45 @@
 use crate::{
-    http::{Request, Response},
+    http::{Request, Response, IntoResponse},
     error::Result,
     service::Service,
 };
-use std::future::Future;
 use std::marker::PhantomData;
 
-pub struct FnHandler<F, Fut, State = ()>
-where
-    F: Fn(Request, &State) -> Fut,
-    Fut: Future<Output = Result<Response>>,
-{
+/// Adapts an async function into a [`Service`]. The function may return
+/// anything implementing [`IntoResponse`].
+pub struct FnHandler<F, State = ()> {
     f: F,
-    _fut: PhantomData<Fut>,
     _state: PhantomData<State>,
 }
 
-impl<F, Fut, State> FnHandler<F, Fut, State>
-where
-    F: Fn(Request, &State) -> Fut,
-    Fut: Future<Output = Result<Response>>,
-{
-    pub fn new(f: F) -> Self {
+impl<F, State> FnHandler<F, State> {
+    pub fn new<R>(f: F) -> Self
+    where
+        F: AsyncFn(Request, &State) -> R,
+        R: IntoResponse,
+    {
         Self {
             f,
-            _fut: PhantomData,
             _state: PhantomData,
         }
     }
 }
 
-impl<F, Fut, State> Service<State> for FnHandler<F, Fut, State>
+impl<F, R, State> Service<State> for FnHandler<F, State>
 where
-    F: Fn(Request, &State) -> Fut,
-    Fut: Future<Output = Result<Response>>,
+    F: AsyncFn(Request, &State) -> R,
+    R: IntoResponse,
 {
     async fn call(&self, req: Request, state: &State) -> Result<Response> {
-        (self.f)(req, state).await
+        (self.f)(req, state).await.into_response()
     }
 }
 
-impl<F, Fut, State> Clone for FnHandler<F, Fut, State>
-where
-    F: Fn(Request, &State) -> Fut + Clone,
-    Fut: Future<Output = Result<Response>>,
-{
+impl<F: Clone, State> Clone for FnHandler<F, State> {
     fn clone(&self) -> Self {
         Self {
             f: self.f.clone(),
-            _fut: PhantomData,
             _state: PhantomData,
         }
     }
-}
\ No newline at end of file
+}
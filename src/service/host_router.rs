@@ -0,0 +1,274 @@
+use crate::{
+    http::{BodyMode, Request, Response},
+    error::{Result, Error},
+    service::{Service, Nil},
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Label {
+    Static(String),
+    Param(String),
+}
+
+/// A parsed host such as `api.example.com` or `:tenant.example.com`.
+///
+/// Labels compare case-insensitively and `:name` captures a single label.
+/// The pattern `*` matches any host, including requests without one.
+#[derive(Debug, Clone)]
+pub struct HostPattern {
+    raw: String,
+    labels: Option<Vec<Label>>,
+}
+
+impl HostPattern {
+    pub fn parse(host: &str) -> Self {
+        let labels = (host != "*").then(|| {
+            host.split('.')
+                .map(|label| match label.strip_prefix(':') {
+                    Some("") => panic!("unnamed parameter in host `{}`", host),
+                    Some(name) => Label::Param(name.to_string()),
+                    None => Label::Static(label.to_ascii_lowercase()),
+                })
+                .collect()
+        });
+
+        Self {
+            raw: host.to_string(),
+            labels,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn matches(&self, host: Option<&str>) -> bool {
+        self.captures(host).is_some()
+    }
+
+    pub fn captures(&self, host: Option<&str>) -> Option<HashMap<String, String>> {
+        let Some(labels) = &self.labels else {
+            return Some(HashMap::new());
+        };
+        let host: Vec<&str> = host?.split('.').collect();
+        if host.len() != labels.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (label, value) in labels.iter().zip(host) {
+            match label {
+                Label::Static(s) if s.eq_ignore_ascii_case(value) => (),
+                Label::Static(_) => return None,
+                Label::Param(name) => {
+                    params.insert(name.clone(), value.to_string());
+                }
+            }
+        }
+        Some(params)
+    }
+
+    /// Orders two patterns matching the same host, comparing labels from the
+    /// top-level domain down so that static labels beat params.
+    pub fn specificity(&self, other: &HostPattern) -> Ordering {
+        match (&self.labels, &other.labels) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => {
+                for (a, b) in a.iter().rev().zip(b.iter().rev()) {
+                    match (a, b) {
+                        (Label::Static(_), Label::Param(_)) => return Ordering::Greater,
+                        (Label::Param(_), Label::Static(_)) => return Ordering::Less,
+                        _ => (),
+                    }
+                }
+                Ordering::Equal
+            }
+        }
+    }
+
+    /// Whether both patterns match exactly the same set of hosts.
+    pub fn is_equivalent(&self, other: &HostPattern) -> bool {
+        match (&self.labels, &other.labels) {
+            (None, None) => true,
+            (Some(a), Some(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b.iter()).all(|pair| match pair {
+                        (Label::Static(a), Label::Static(b)) => a == b,
+                        (Label::Param(_), Label::Param(_)) => true,
+                        _ => false,
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Dispatches requests to a service per virtual host.
+///
+/// Host parameters are exposed through `Request::host_param`.
+///
+/// ```ignore
+/// HostRouter::new()
+///     .host("api.example.com", api)
+///     .host(":tenant.example.com", tenants)
+///     .host("*", fallback)
+/// ```
+pub struct HostRouter<S, N = Nil> {
+    node: N,
+    _marker: PhantomData<S>,
+}
+
+impl<S> HostRouter<S, Nil> {
+    pub fn new() -> Self {
+        Self {
+            node: Nil,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for HostRouter<S, Nil> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, N> HostRouter<S, N> {
+    pub fn host<H: Service<S>>(self, pattern: &str, service: H) -> HostRouter<S, HostNode<H, N>> {
+        HostRouter {
+            node: HostNode {
+                pattern: HostPattern::parse(pattern),
+                service,
+                next: self.node,
+            },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S, N: HostRouteNode<S>> HostRouter<S, N> {
+    /// Checks that no host pattern is shadowed by an earlier equivalent one.
+    pub fn validate(&self) -> Result<()> {
+        let mut hosts = Vec::new();
+        self.node.for_each_host(&mut |pattern| hosts.push(pattern));
+        hosts.reverse();
+
+        for (i, pattern) in hosts.iter().enumerate() {
+            if let Some(existing) = hosts[..i].iter().find(|p| p.is_equivalent(pattern)) {
+                return Err(Error::RouteConflict(format!(
+                    "host {} is unreachable, it is shadowed by {}",
+                    pattern.as_str(), existing.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S, N: HostRouteNode<S>> Service<S> for HostRouter<S, N> {
+    async fn call(&self, req: Request, state: &S) -> Result<Response> {
+        let mut best = None;
+        self.node.find(req.host(), 0, &mut best);
+
+        match best {
+            Some((depth, _)) => self.node.dispatch(depth, req, state).await,
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        let mut best = None;
+        self.node.find(req.host(), 0, &mut best);
+
+        match best {
+            Some((depth, _)) => self.node.before_body(depth, req, state).await,
+            None => Ok(BodyMode::Buffer),
+        }
+    }
+}
+
+pub trait HostRouteNode<S> {
+    fn find<'a>(&'a self, host: Option<&str>, depth: usize, best: &mut Option<(usize, &'a HostPattern)>);
+
+    async fn dispatch(&self, depth: usize, req: Request, state: &S) -> Result<Response>;
+
+    /// `Service::before_body` of the service found at `depth`.
+    async fn before_body(&self, depth: usize, req: &Request, state: &S) -> Result<BodyMode>;
+
+    fn for_each_host<'a, F: FnMut(&'a HostPattern)>(&'a self, f: &mut F);
+}
+
+pub struct HostNode<H, N> {
+    pattern: HostPattern,
+    service: H,
+    next: N,
+}
+
+impl<S> HostRouteNode<S> for Nil {
+    fn find<'a>(&'a self, _host: Option<&str>, _depth: usize, _best: &mut Option<(usize, &'a HostPattern)>) {}
+
+    async fn dispatch(&self, _depth: usize, _req: Request, _state: &S) -> Result<Response> {
+        Err(Error::NotFound)
+    }
+
+    async fn before_body(&self, _depth: usize, _req: &Request, _state: &S) -> Result<BodyMode> {
+        Ok(BodyMode::Buffer)
+    }
+
+    fn for_each_host<'a, F: FnMut(&'a HostPattern)>(&'a self, _f: &mut F) {}
+}
+
+impl<S, H, N> HostRouteNode<S> for HostNode<H, N>
+where
+    H: Service<S>,
+    N: HostRouteNode<S>,
+{
+    fn find<'a>(&'a self, host: Option<&str>, depth: usize, best: &mut Option<(usize, &'a HostPattern)>) {
+        if self.pattern.matches(host) {
+            let replace = match best {
+                Some((_, current)) => self.pattern.specificity(current).is_ge(),
+                None => true,
+            };
+            if replace {
+                *best = Some((depth, &self.pattern));
+            }
+        }
+        self.next.find(host, depth + 1, best);
+    }
+
+    async fn dispatch(&self, depth: usize, mut req: Request, state: &S) -> Result<Response> {
+        if depth > 0 {
+            return self.next.dispatch(depth - 1, req, state).await;
+        }
+
+        if let Some(params) = self.pattern.captures(req.host()).filter(|p| !p.is_empty()) {
+            req.set_host_params(params);
+        }
+        self.service.call(req, state).await
+    }
+
+    async fn before_body(&self, depth: usize, req: &Request, state: &S) -> Result<BodyMode> {
+        if depth > 0 {
+            return self.next.before_body(depth - 1, req, state).await;
+        }
+
+        match self.pattern.captures(req.host()).filter(|p| !p.is_empty()) {
+            Some(params) => {
+                let mut req = req.clone();
+                req.set_host_params(params);
+                self.service.before_body(&req, state).await
+            }
+            None => self.service.before_body(req, state).await,
+        }
+    }
+
+    fn for_each_host<'a, F: FnMut(&'a HostPattern)>(&'a self, f: &mut F) {
+        f(&self.pattern);
+        self.next.for_each_host(f);
+    }
+}
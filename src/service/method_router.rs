@@ -0,0 +1,196 @@
+use crate::{
+    http::{BodyMode, Request, Response},
+    error::{Result, Error},
+    service::{Service, Nil},
+};
+use http::Method;
+use std::marker::PhantomData;
+
+/// Dispatches a single path to different handlers by request method.
+///
+/// ```ignore
+/// Router::new().at("/users/:id", get(Show).put(Update).delete(Destroy))
+/// ```
+pub struct MethodRouter<S, N = Nil> {
+    node: N,
+    _marker: PhantomData<S>,
+}
+
+impl<S> MethodRouter<S, Nil> {
+    pub fn new() -> Self {
+        Self {
+            node: Nil,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for MethodRouter<S, Nil> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, N> MethodRouter<S, N> {
+    pub fn on<H: Service<S>>(self, method: Method, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        MethodRouter {
+            node: MethodNode {
+                method,
+                handler,
+                next: self.node,
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn get<H: Service<S>>(self, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        self.on(Method::GET, handler)
+    }
+
+    pub fn post<H: Service<S>>(self, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        self.on(Method::POST, handler)
+    }
+
+    pub fn put<H: Service<S>>(self, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        self.on(Method::PUT, handler)
+    }
+
+    pub fn delete<H: Service<S>>(self, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        self.on(Method::DELETE, handler)
+    }
+
+    pub fn patch<H: Service<S>>(self, handler: H) -> MethodRouter<S, MethodNode<H, N>> {
+        self.on(Method::PATCH, handler)
+    }
+}
+
+pub fn on<S, H: Service<S>>(method: Method, handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    MethodRouter::new().on(method, handler)
+}
+
+pub fn get<S, H: Service<S>>(handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    on(Method::GET, handler)
+}
+
+pub fn post<S, H: Service<S>>(handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    on(Method::POST, handler)
+}
+
+pub fn put<S, H: Service<S>>(handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    on(Method::PUT, handler)
+}
+
+pub fn delete<S, H: Service<S>>(handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    on(Method::DELETE, handler)
+}
+
+pub fn patch<S, H: Service<S>>(handler: H) -> MethodRouter<S, MethodNode<H, Nil>> {
+    on(Method::PATCH, handler)
+}
+
+impl<S, N: MethodEndpoint<S>> MethodRouter<S, N> {
+    pub fn allows(&self, method: &Method) -> bool {
+        self.node.allows(method)
+    }
+
+    pub(crate) fn into_node(self) -> N {
+        self.node
+    }
+}
+
+impl<S, N: MethodEndpoint<S>> Service<S> for MethodRouter<S, N> {
+    async fn call(&self, req: Request, state: &S) -> Result<Response> {
+        if !self.node.allows(req.method()) {
+            return Err(Error::MethodNotAllowed(allowed_methods(&self.node)));
+        }
+        self.node.dispatch(req, state).await
+    }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        self.node.before_body(req, state).await
+    }
+}
+
+/// The methods registered on `node`, in registration order.
+pub(crate) fn allowed_methods<S, N: MethodEndpoint<S>>(node: &N) -> Vec<Method> {
+    let mut methods = Vec::new();
+    collect_methods(node, &mut methods);
+    methods
+}
+
+/// Adds the methods of `node` missing from `methods`, in registration order.
+pub(crate) fn collect_methods<S, N: MethodEndpoint<S>>(node: &N, methods: &mut Vec<Method>) {
+    let mut found = Vec::new();
+    node.for_each_method(&mut |method| found.push(method));
+    // The chain yields the most recently registered method first.
+    for method in found.into_iter().rev() {
+        if !methods.contains(method) {
+            methods.push(method.clone());
+        }
+    }
+}
+
+pub trait MethodEndpoint<S> {
+    fn allows(&self, method: &Method) -> bool;
+
+    async fn dispatch(&self, req: Request, state: &S) -> Result<Response>;
+
+    /// `Service::before_body` of the handler `dispatch` would pick.
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode>;
+
+    fn for_each_method<'a, F: FnMut(&'a Method)>(&'a self, f: &mut F);
+}
+
+pub struct MethodNode<H, N> {
+    method: Method,
+    handler: H,
+    next: N,
+}
+
+impl<S> MethodEndpoint<S> for Nil {
+    fn allows(&self, _method: &Method) -> bool {
+        false
+    }
+
+    async fn dispatch(&self, _req: Request, _state: &S) -> Result<Response> {
+        Err(Error::MethodNotAllowed(Vec::new()))
+    }
+
+    async fn before_body(&self, _req: &Request, _state: &S) -> Result<BodyMode> {
+        Ok(BodyMode::Buffer)
+    }
+
+    fn for_each_method<'a, F: FnMut(&'a Method)>(&'a self, _f: &mut F) {}
+}
+
+impl<S, H, N> MethodEndpoint<S> for MethodNode<H, N>
+where
+    H: Service<S>,
+    N: MethodEndpoint<S>,
+{
+    fn allows(&self, method: &Method) -> bool {
+        *method == self.method || self.next.allows(method)
+    }
+
+    async fn dispatch(&self, req: Request, state: &S) -> Result<Response> {
+        // A duplicate method keeps its first registration, as in `Router`.
+        if *req.method() == self.method && !self.next.allows(req.method()) {
+            self.handler.call(req, state).await
+        } else {
+            self.next.dispatch(req, state).await
+        }
+    }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        if *req.method() == self.method && !self.next.allows(req.method()) {
+            self.handler.before_body(req, state).await
+        } else {
+            self.next.before_body(req, state).await
+        }
+    }
+
+    fn for_each_method<'a, F: FnMut(&'a Method)>(&'a self, f: &mut F) {
+        f(&self.method);
+        self.next.for_each_method(f);
+    }
+}
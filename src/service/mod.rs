@@ -1,14 +1,32 @@
 mod router;
 mod handler;
+mod pattern;
+mod method_router;
+mod host_router;
+mod serve_dir;
 
-pub use router::{Router, Nil, Node, RouteNode};
+pub use router::{Router, Nil, Node, Route, RouteNode, RouteInfo, Lookup, PathPolicy};
+pub use method_router::{MethodRouter, MethodNode, MethodEndpoint, on, get, post, put, delete, patch};
+pub use pattern::{Pattern, Segment, normalize_path};
+pub use host_router::{HostRouter, HostNode, HostRouteNode, HostPattern};
 pub use handler::FnHandler;
+pub use serve_dir::{ServeDir, ServeFile, guess_mime};
 
 use crate::{
-    http::{Request, Response},
+    http::{BodyMode, Request, Response},
     error::Result,
 };
 
 pub trait Service<State = ()> {
     async fn call(&self, req: Request, state: &State) -> Result<Response>;
+
+    /// Looks at a request after its head is read and before its body is.
+    ///
+    /// An error is sent as the response without the body ever being read.
+    /// Otherwise the mode tells the server whether to buffer the body for
+    /// `call` or to leave it on the connection. Routers and middleware pass
+    /// this on to the service that will handle the request.
+    async fn before_body(&self, _req: &Request, _state: &State) -> Result<BodyMode> {
+        Ok(BodyMode::Buffer)
+    }
 }
\ No newline at end of file
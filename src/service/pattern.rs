@@ -0,0 +1,237 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::error::{Error, Result};
+
+/// Everything but the RFC 3986 unreserved characters.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+impl Segment {
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 2,
+            Segment::Param(_) => 1,
+            Segment::Wildcard(_) => 0,
+        }
+    }
+
+    fn same_shape(&self, other: &Segment) -> bool {
+        match (self, other) {
+            (Segment::Static(a), Segment::Static(b)) => a == b,
+            (Segment::Param(_), Segment::Param(_)) => true,
+            (Segment::Wildcard(_), Segment::Wildcard(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A parsed route path such as `/users/:id` or `/assets/*path`.
+///
+/// `:name` captures a single segment and `*name` captures the remainder of
+/// the path, so a wildcard is only allowed as the last segment.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    raw: String,
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    pub fn parse(path: &str) -> Self {
+        let segments: Vec<Segment> = split_path(path)
+            .map(|s| {
+                if let Some(name) = s.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = s.strip_prefix('*') {
+                    Segment::Wildcard(name.to_string())
+                } else {
+                    Segment::Static(s.to_string())
+                }
+            })
+            .collect();
+
+        for (i, segment) in segments.iter().enumerate() {
+            match segment {
+                Segment::Param(name) | Segment::Wildcard(name) if name.is_empty() => {
+                    panic!("unnamed parameter in route `{}`", path);
+                }
+                Segment::Wildcard(_) if i + 1 != segments.len() => {
+                    panic!("wildcard must be the last segment in route `{}`", path);
+                }
+                _ => (),
+            }
+        }
+
+        Self {
+            raw: path.to_string(),
+            segments,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn matches(&self, path: &[&str]) -> bool {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Static(s) => {
+                    if path.get(i) != Some(&s.as_str()) {
+                        return false;
+                    }
+                }
+                Segment::Param(_) => {
+                    if path.get(i).is_none() {
+                        return false;
+                    }
+                }
+                Segment::Wildcard(_) => return true,
+            }
+        }
+        self.segments.len() == path.len()
+    }
+
+    pub fn captures(&self, path: &[&str]) -> Option<HashMap<String, String>> {
+        if !self.matches(path) {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Param(name) => {
+                    params.insert(name.clone(), decode(path[i]));
+                }
+                Segment::Wildcard(name) => {
+                    params.insert(name.clone(), decode(&path[i..].join("/")));
+                }
+                Segment::Static(_) => (),
+            }
+        }
+        Some(params)
+    }
+
+    /// Orders two patterns that match the same path: the first segment that
+    /// differs decides, with static beating param beating wildcard.
+    pub fn specificity(&self, other: &Pattern) -> Ordering {
+        for (a, b) in self.segments.iter().zip(other.segments.iter()) {
+            match a.rank().cmp(&b.rank()) {
+                Ordering::Equal => (),
+                ord => return ord,
+            }
+        }
+        self.segments.len().cmp(&other.segments.len())
+    }
+
+    /// Whether both patterns match exactly the same set of paths.
+    pub fn is_equivalent(&self, other: &Pattern) -> bool {
+        self.segments.len() == other.segments.len()
+            && self.segments.iter()
+                .zip(other.segments.iter())
+                .all(|(a, b)| a.same_shape(b))
+    }
+
+    /// Builds a path from the pattern, percent-encoding each parameter.
+    ///
+    /// Wildcard values may contain `/` to span several segments. Parameters
+    /// the pattern does not use are ignored. Values (or wildcard segments)
+    /// of `.` or `..` are rejected, as the resulting path would not match
+    /// the route.
+    pub fn url(&self, params: &[(&str, &str)]) -> Result<String> {
+        let lookup = |name: &str| {
+            params.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| Error::UrlGeneration(format!(
+                    "missing parameter `{}` for route `{}`", name, self.raw
+                )))
+        };
+
+        let mut url = String::new();
+        for segment in &self.segments {
+            url.push('/');
+            match segment {
+                Segment::Static(s) => url.push_str(s),
+                Segment::Param(name) => push_encoded(&mut url, lookup(name)?, &self.raw)?,
+                Segment::Wildcard(name) => {
+                    for (i, part) in lookup(name)?.split('/').enumerate() {
+                        if i > 0 {
+                            url.push('/');
+                        }
+                        push_encoded(&mut url, part, &self.raw)?;
+                    }
+                }
+            }
+        }
+
+        if url.is_empty() {
+            url.push('/');
+        }
+        Ok(url)
+    }
+}
+
+fn push_encoded(url: &mut String, value: &str, raw: &str) -> Result<()> {
+    // Request paths are normalized before matching, which would resolve
+    // these however they are encoded, so the URL could never route back.
+    if dot_count(value).is_some() {
+        return Err(Error::UrlGeneration(format!(
+            "parameter value `{}` for route `{}` is a dot segment", value, raw
+        )));
+    }
+    url.extend(utf8_percent_encode(value, SEGMENT));
+    Ok(())
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+pub fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+/// Resolves `.` and `..` segments (including percent-encoded dots) and drops
+/// empty ones, so `/a//b/../c/` becomes `/a/c`. This is the form routes are
+/// matched against.
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in split_path(path) {
+        match dot_count(segment) {
+            Some(1) => (),
+            Some(_) => {
+                segments.pop();
+            }
+            None => segments.push(segment),
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+fn dot_count(segment: &str) -> Option<usize> {
+    let mut rest = segment;
+    let mut dots = 0;
+    while !rest.is_empty() && dots < 2 {
+        rest = rest.strip_prefix('.')
+            .or_else(|| rest.strip_prefix("%2e"))
+            .or_else(|| rest.strip_prefix("%2E"))?;
+        dots += 1;
+    }
+    (rest.is_empty() && dots > 0).then_some(dots)
+}
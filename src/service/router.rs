@@ -1,118 +1,363 @@
 use crate::{
-    http::{Request, Response},
+    http::{BodyMode, Request, Response},
     error::{Result, Error},
-    service::Service,
+    service::{
+        Service,
+        pattern::{Pattern, split_path, normalize_path},
+        method_router::{self, MethodRouter, MethodNode, MethodEndpoint},
+    },
 };
 use http::Method;
-use std::collections::HashMap;
 use std::marker::PhantomData;
 
+/// Statically typed router.
+///
+/// Every request is checked against all registered routes and the most
+/// specific match wins (static segments over `:param` over `*wildcard`),
+/// independent of registration order. A path that matches but has no handler
+/// for the request method yields `Error::MethodNotAllowed`. Routes that can
+/// never be reached are reported by [`Router::validate`].
+///
+/// Paths are matched after normalization (see [`PathPolicy`]).
 pub struct Router<S, N = Nil> {
     node: N,
+    policy: PathPolicy,
     _marker: PhantomData<S>,
 }
 
 pub struct Nil;
 
+/// How a router treats request paths that are not in canonical form.
+///
+/// The canonical form has `.` and `..` segments resolved, no empty segments
+/// and no trailing slash, e.g. `/users/42` for `/users//7/../42/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathPolicy {
+    /// Only canonical paths match; anything else is not found.
+    Strict,
+    /// Non-canonical paths that would match are answered with a
+    /// `308 Permanent Redirect` to the canonical path.
+    Redirect,
+    /// Non-canonical paths are matched as if they were canonical.
+    #[default]
+    Lenient,
+}
+
 impl<S> Router<S, Nil> {
     pub fn new() -> Self {
         Self {
             node: Nil,
+            policy: PathPolicy::default(),
             _marker: PhantomData,
         }
     }
 }
 
+impl<S> Default for Router<S, Nil> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<S, N> Router<S, N> {
-    pub fn route<H>(self, method: Method, path: &str, handler: H) -> Router<S, Node<H, N>>
+    pub fn path_policy(mut self, policy: PathPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Registers a [`MethodRouter`] so the path is matched once and the
+    /// request then dispatched on its method.
+    ///
+    /// This is the `route(path, get(..).post(..))` form. It is named `at`
+    /// because [`Router::route`] already takes a method, path and handler,
+    /// and keeps that signature for existing callers.
+    pub fn at<M>(self, path: &str, methods: MethodRouter<S, M>) -> Router<S, Node<M, N>>
     where
-        H: Service<S>,
+        M: MethodEndpoint<S>,
     {
         Router {
             node: Node {
-                method,
-                path: path.to_string(),
-                handler,
+                pattern: Pattern::parse(path),
+                name: None,
+                methods: methods.into_node(),
                 next: self.node,
             },
+            policy: self.policy,
             _marker: PhantomData,
         }
     }
+
+    pub fn route<H>(self, method: Method, path: &str, handler: H) -> Router<S, Route<H, N>>
+    where
+        H: Service<S>,
+    {
+        self.at(path, method_router::on(method, handler))
+    }
+
+    pub fn get<H: Service<S>>(self, path: &str, handler: H) -> Router<S, Route<H, N>> {
+        self.route(Method::GET, path, handler)
+    }
+
+    pub fn post<H: Service<S>>(self, path: &str, handler: H) -> Router<S, Route<H, N>> {
+        self.route(Method::POST, path, handler)
+    }
+
+    pub fn put<H: Service<S>>(self, path: &str, handler: H) -> Router<S, Route<H, N>> {
+        self.route(Method::PUT, path, handler)
+    }
+
+    pub fn delete<H: Service<S>>(self, path: &str, handler: H) -> Router<S, Route<H, N>> {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    pub fn patch<H: Service<S>>(self, path: &str, handler: H) -> Router<S, Route<H, N>> {
+        self.route(Method::PATCH, path, handler)
+    }
+}
+
+impl<S, M, N> Router<S, Node<M, N>> {
+    /// Names the most recently registered route for [`Router::url_for`].
+    pub fn name(mut self, name: &str) -> Self {
+        self.node.name = Some(name.to_string());
+        self
+    }
 }
 
-impl<S, N> Service<S> for Router<S, N> 
-where 
+impl<S, N: RouteNode<S>> Router<S, N> {
+    /// Lists every method and pattern in registration order.
+    pub fn routes(&self) -> Vec<RouteInfo<'_>> {
+        let mut routes = Vec::new();
+        self.node.for_each_route(&mut |info| routes.push(info));
+        // The chain yields the most recently registered route first.
+        routes.reverse();
+        routes
+    }
+
+    /// Builds the path of a named route from its parameters.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String> {
+        let mut pattern = None;
+        self.node.for_each_route(&mut |info| {
+            if info.name() == Some(name) {
+                pattern = Some(info.pattern());
+            }
+        });
+
+        pattern
+            .ok_or_else(|| Error::UrlGeneration(format!("no route named `{}`", name)))?
+            .url(params)
+    }
+
+    /// Checks that every registered route is reachable and route names are
+    /// unique.
+    ///
+    /// Two routes with the same method and the same shape (param names
+    /// aside) would shadow each other, so the later one is reported as a
+    /// conflict.
+    pub fn validate(&self) -> Result<()> {
+        let routes = self.routes();
+
+        for (i, route) in routes.iter().enumerate() {
+            let earlier = &routes[..i];
+            let shadowing = earlier.iter()
+                .find(|r| r.method() == route.method() && r.pattern().is_equivalent(route.pattern()));
+            if let Some(existing) = shadowing {
+                return Err(Error::RouteConflict(format!(
+                    "{} {} is unreachable, it is shadowed by {} {}",
+                    route.method(), route.pattern().as_str(), existing.method(), existing.pattern().as_str()
+                )));
+            }
+
+            let same_name = earlier.iter()
+                .find(|r| r.name().is_some() && r.name() == route.name() && !std::ptr::eq(r.pattern(), route.pattern()));
+            if let Some(existing) = same_name {
+                return Err(Error::RouteConflict(format!(
+                    "route name `{}` is used by both {} and {}",
+                    route.name().unwrap_or_default(), existing.pattern().as_str(), route.pattern().as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Methods of every route matching `path`, for the `Allow` header.
+    fn allowed_methods(&self, path: &[&str]) -> Vec<Method> {
+        let mut methods = Vec::new();
+        self.node.collect_methods(path, &mut methods);
+        methods
+    }
+}
+
+impl<S, N> Service<S> for Router<S, N>
+where
     N: RouteNode<S>,
 {
     async fn call(&self, req: Request, state: &S) -> Result<Response> {
-        self.node.match_route(req, state).await
+        let canonical = normalize_path(req.uri().path());
+        let is_canonical = canonical == req.uri().path();
+        let path: Vec<&str> = split_path(&canonical).collect();
+
+        let mut lookup = Lookup::default();
+        self.node.find(req.method(), &path, 0, &mut lookup);
+
+        match (lookup.best, self.policy) {
+            (_, PathPolicy::Strict) if !is_canonical => Err(Error::NotFound),
+            (Some(_), PathPolicy::Redirect) if !is_canonical => redirect_to(&canonical, &req),
+            (Some((depth, _)), _) => self.node.dispatch(depth, &path, req, state).await,
+            (None, _) if lookup.path_matched => Err(Error::MethodNotAllowed(self.allowed_methods(&path))),
+            (None, _) => Err(Error::NotFound),
+        }
     }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        let canonical = normalize_path(req.uri().path());
+        let path: Vec<&str> = split_path(&canonical).collect();
+        if canonical != req.uri().path() && self.policy != PathPolicy::Lenient {
+            return Ok(BodyMode::Buffer);
+        }
+
+        let mut lookup = Lookup::default();
+        self.node.find(req.method(), &path, 0, &mut lookup);
+        match lookup.best {
+            Some((depth, _)) => self.node.before_body(depth, &path, req, state).await,
+            None => Ok(BodyMode::Buffer),
+        }
+    }
+}
+
+fn redirect_to(path: &str, req: &Request) -> Result<Response> {
+    let location = match req.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+
+    Response::permanent_redirect(&location)
+}
+
+/// A registered route as reported by [`Router::routes`].
+#[derive(Debug, Clone, Copy)]
+pub struct RouteInfo<'a> {
+    method: &'a Method,
+    pattern: &'a Pattern,
+    name: Option<&'a str>,
+}
+
+impl<'a> RouteInfo<'a> {
+    pub fn method(&self) -> &'a Method { self.method }
+    pub fn pattern(&self) -> &'a Pattern { self.pattern }
+    pub fn name(&self) -> Option<&'a str> { self.name }
+}
+
+/// Result of walking the route chain for one request.
+#[derive(Default)]
+pub struct Lookup<'a> {
+    best: Option<(usize, &'a Pattern)>,
+    path_matched: bool,
 }
 
 pub trait RouteNode<S> {
-    async fn match_route(&self, req: Request, state: &S) -> Result<Response>;
+    /// Records the most specific route matching `method` and `path` in
+    /// `lookup` as its depth in the chain.
+    fn find<'a>(&'a self, method: &Method, path: &[&str], depth: usize, lookup: &mut Lookup<'a>);
+
+    /// Calls the route found at `depth`, capturing params from the
+    /// normalized `path`.
+    async fn dispatch(&self, depth: usize, path: &[&str], req: Request, state: &S) -> Result<Response>;
+
+    /// `Service::before_body` of the route found at `depth`.
+    async fn before_body(&self, depth: usize, path: &[&str], req: &Request, state: &S) -> Result<BodyMode>;
+
+    fn for_each_route<'a, F: FnMut(RouteInfo<'a>)>(&'a self, f: &mut F);
+
+    /// Adds the methods of the routes matching `path` to `methods`, earliest
+    /// registration first.
+    fn collect_methods(&self, path: &[&str], methods: &mut Vec<Method>);
 }
 
-pub struct Node<H, N> {
-    method: Method,
-    path: String,
-    handler: H,
+/// A route holding a single method handler, as built by [`Router::route`].
+pub type Route<H, N> = Node<MethodNode<H, Nil>, N>;
+
+pub struct Node<M, N> {
+    pattern: Pattern,
+    name: Option<String>,
+    methods: M,
     next: N,
 }
 
 impl<S> RouteNode<S> for Nil {
-    async fn match_route(&self, _req: Request, _state: &S) -> Result<Response> {
+    fn find<'a>(&'a self, _method: &Method, _path: &[&str], _depth: usize, _lookup: &mut Lookup<'a>) {}
+
+    async fn dispatch(&self, _depth: usize, _path: &[&str], _req: Request, _state: &S) -> Result<Response> {
         Err(Error::NotFound)
     }
+
+    async fn before_body(&self, _depth: usize, _path: &[&str], _req: &Request, _state: &S) -> Result<BodyMode> {
+        Ok(BodyMode::Buffer)
+    }
+
+    fn for_each_route<'a, F: FnMut(RouteInfo<'a>)>(&'a self, _f: &mut F) {}
+
+    fn collect_methods(&self, _path: &[&str], _methods: &mut Vec<Method>) {}
 }
 
-impl<S, H, N> RouteNode<S> for Node<H, N>
+impl<S, M, N> RouteNode<S> for Node<M, N>
 where
-    H: Service<S>,
+    M: MethodEndpoint<S>,
     N: RouteNode<S>,
 {
-    async fn match_route(&self, req: Request, state: &S) -> Result<Response> {
-        let method = req.method();
-        let path = req.uri().path().to_string();
-        
-        if *method == self.method {
-            if !self.path.contains(':') {
-                if self.path == path {
-                    return self.handler.call(req, state).await;
-                }
-            } else {
-                let mut req_clone = req.clone();
-                if extract_path_match(&self.path, &path, &mut req_clone) {
-                    return self.handler.call(req_clone, state).await;
+    fn find<'a>(&'a self, method: &Method, path: &[&str], depth: usize, lookup: &mut Lookup<'a>) {
+        if self.pattern.matches(path) {
+            lookup.path_matched = true;
+            if self.methods.allows(method) {
+                // Ties go to the earlier registration, which sits deeper in the chain.
+                let replace = match lookup.best {
+                    Some((_, current)) => self.pattern.specificity(current).is_ge(),
+                    None => true,
+                };
+                if replace {
+                    lookup.best = Some((depth, &self.pattern));
                 }
             }
         }
-        
-        self.next.match_route(req, state).await
-    }
-}
-
-fn extract_path_match(route_path: &str, req_path: &str, req: &mut Request) -> bool {
-    let route_segments: Vec<&str> = route_path.split('/').filter(|s| !s.is_empty()).collect();
-    let req_segments: Vec<&str> = req_path.split('/').filter(|s| !s.is_empty()).collect();
-    
-    if route_segments.len() != req_segments.len() {
-        return false;
-    }
-    
-    let mut params = HashMap::new();
-    for (i, segment) in route_segments.iter().enumerate() {
-        if segment.starts_with(':') {
-            let param_name = &segment[1..];
-            params.insert(param_name.to_string(), req_segments[i].to_string());
-        } else if *segment != req_segments[i] {
-            return false;
+        self.next.find(method, path, depth + 1, lookup);
+    }
+
+    async fn dispatch(&self, depth: usize, path: &[&str], mut req: Request, state: &S) -> Result<Response> {
+        if depth > 0 {
+            return self.next.dispatch(depth - 1, path, req, state).await;
         }
+
+        if let Some(params) = self.pattern.captures(path).filter(|p| !p.is_empty()) {
+            req.set_path_params(params);
+        }
+        self.methods.dispatch(req, state).await
     }
-    
-    if !params.is_empty() {
-        req.set_path_params(params);
+
+    async fn before_body(&self, depth: usize, path: &[&str], req: &Request, state: &S) -> Result<BodyMode> {
+        if depth > 0 {
+            return self.next.before_body(depth - 1, path, req, state).await;
+        }
+
+        match self.pattern.captures(path).filter(|p| !p.is_empty()) {
+            Some(params) => {
+                let mut req = req.clone();
+                req.set_path_params(params);
+                self.methods.before_body(&req, state).await
+            }
+            None => self.methods.before_body(req, state).await,
+        }
+    }
+
+    fn for_each_route<'a, F: FnMut(RouteInfo<'a>)>(&'a self, f: &mut F) {
+        let pattern = &self.pattern;
+        let name = self.name.as_deref();
+        self.methods.for_each_method(&mut |method| f(RouteInfo { method, pattern, name }));
+        self.next.for_each_route(f);
     }
-    
-    true
-}
\ No newline at end of file
+
+    fn collect_methods(&self, path: &[&str], methods: &mut Vec<Method>) {
+        self.next.collect_methods(path, methods);
+        if self.pattern.matches(path) {
+            method_router::collect_methods(&self.methods, methods);
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use monoio::fs::File;
+
+use crate::{
+    error::{Error, Result},
+    http::{FilePart, FileRegion, Request, Response},
+    service::Service,
+};
+
+/// More ranges than this in one request are answered with the whole file.
+const MAX_RANGES: usize = 16;
+
+/// Serves files below a root directory.
+///
+/// The file is named by the `path` capture of a wildcard route
+/// (`/static/*path`), or by the whole request path when there is none.
+/// Paths with `..` segments, backslashes or NUL bytes are rejected, and
+/// directories are answered with their index file. A directory requested
+/// without a trailing slash is redirected to the path with one, which
+/// needs the router's default `PathPolicy::Lenient`.
+///
+/// ```ignore
+/// Router::new()
+///     .get("/static/*path", ServeDir::new("public").precompressed_br())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    param: String,
+    index_files: Vec<String>,
+    options: ServeOptions,
+}
+
+impl ServeDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            param: "path".to_string(),
+            index_files: vec!["index.html".to_string()],
+            options: ServeOptions::default(),
+        }
+    }
+
+    /// Name of the path parameter holding the file path, `path` by default.
+    pub fn param(mut self, name: impl Into<String>) -> Self {
+        self.param = name.into();
+        self
+    }
+
+    /// Files tried, in order, when a directory is requested. Replaces the
+    /// default `index.html`; pass an empty slice to disable.
+    pub fn index_files(mut self, names: &[&str]) -> Self {
+        self.index_files = names.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Serves `file.gz` instead of `file` to clients accepting gzip.
+    pub fn precompressed_gzip(mut self) -> Self {
+        self.options.gzip = true;
+        self
+    }
+
+    /// Serves `file.br` instead of `file` to clients accepting brotli.
+    pub fn precompressed_br(mut self) -> Self {
+        self.options.brotli = true;
+        self
+    }
+
+    fn resolve(&self, req: &Request) -> Result<PathBuf> {
+        let requested = match req.path_param(&self.param) {
+            Some(path) => path.to_string(),
+            None => percent_encoding::percent_decode_str(req.uri().path())
+                .decode_utf8()
+                .map_err(|_| Error::NotFound)?
+                .into_owned(),
+        };
+
+        let mut path = self.root.clone();
+        for segment in requested.split('/') {
+            if segment.is_empty() || segment == "." {
+                continue;
+            }
+            if segment == ".." || segment.contains(['\\', '\0']) || Path::new(segment).has_root() {
+                return Err(Error::NotFound);
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+}
+
+impl<S> Service<S> for ServeDir {
+    async fn call(&self, req: Request, _state: &S) -> Result<Response> {
+        check_method(&req)?;
+        let path = self.resolve(&req)?;
+
+        let is_dir = monoio::fs::metadata(&path).await.map_err(not_found)?.is_dir();
+        if !is_dir {
+            return serve_file(&path, &req, &self.options).await;
+        }
+        if !req.uri().path().ends_with('/') {
+            // Relative links in the index resolve against the directory.
+            let location = match req.uri().query() {
+                Some(query) => format!("{}/?{}", req.uri().path(), query),
+                None => format!("{}/", req.uri().path()),
+            };
+            return Response::moved_permanently(&location);
+        }
+        for index in &self.index_files {
+            let index = path.join(index);
+            match monoio::fs::metadata(&index).await {
+                Ok(meta) if meta.is_file() => return serve_file(&index, &req, &self.options).await,
+                _ => continue,
+            }
+        }
+        Err(Error::NotFound)
+    }
+}
+
+/// Serves a single file regardless of the request path.
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: PathBuf,
+    options: ServeOptions,
+}
+
+impl ServeFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            options: ServeOptions::default(),
+        }
+    }
+
+    /// Overrides the type guessed from the file extension.
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.options.content_type = Some(content_type.to_string());
+        self
+    }
+
+    pub fn precompressed_gzip(mut self) -> Self {
+        self.options.gzip = true;
+        self
+    }
+
+    pub fn precompressed_br(mut self) -> Self {
+        self.options.brotli = true;
+        self
+    }
+}
+
+impl<S> Service<S> for ServeFile {
+    async fn call(&self, req: Request, _state: &S) -> Result<Response> {
+        check_method(&req)?;
+        serve_file(&self.path, &req, &self.options).await
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ServeOptions {
+    gzip: bool,
+    brotli: bool,
+    content_type: Option<String>,
+}
+
+fn check_method(req: &Request) -> Result<()> {
+    match *req.method() {
+        Method::GET | Method::HEAD => Ok(()),
+        _ => Err(Error::MethodNotAllowed(vec![Method::GET, Method::HEAD])),
+    }
+}
+
+fn not_found(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied | io::ErrorKind::NotADirectory => Error::NotFound,
+        _ => Error::Io(err),
+    }
+}
+
+/// The file to send: the original or a precompressed sibling.
+struct Variant {
+    file: File,
+    len: u64,
+    modified: SystemTime,
+    encoding: Option<&'static str>,
+}
+
+async fn open(path: &Path) -> io::Result<Option<(File, u64, SystemTime)>> {
+    let file = File::open(path).await?;
+    let meta = file.metadata().await?;
+    if !meta.is_file() {
+        return Ok(None);
+    }
+    Ok(Some((file, meta.len(), meta.modified()?)))
+}
+
+async fn choose_variant(path: &Path, req: &Request, options: &ServeOptions) -> Result<Variant> {
+    let mut offers = Vec::new();
+    if options.brotli {
+        offers.push("br");
+    }
+    if options.gzip {
+        offers.push("gzip");
+    }
+    if !offers.is_empty() {
+        offers.push("identity");
+        if let Ok(encoding @ ("br" | "gzip")) = req.negotiate_encoding(&offers) {
+            let mut compressed = path.as_os_str().to_owned();
+            compressed.push(if encoding == "br" { ".br" } else { ".gz" });
+            if let Ok(Some((file, len, modified))) = open(Path::new(&compressed)).await {
+                return Ok(Variant { file, len, modified, encoding: Some(encoding) });
+            }
+        }
+    }
+
+    match open(path).await.map_err(not_found)? {
+        Some((file, len, modified)) => Ok(Variant { file, len, modified, encoding: None }),
+        None => Err(Error::NotFound),
+    }
+}
+
+async fn serve_file(path: &Path, req: &Request, options: &ServeOptions) -> Result<Response> {
+    let variant = choose_variant(path, req, options).await?;
+    let etag = etag(&variant);
+    let last_modified = httpdate::fmt_http_date(variant.modified);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, HeaderValue::try_from(etag.as_str()).map_err(http::Error::from)?);
+    headers.insert(header::LAST_MODIFIED, HeaderValue::try_from(last_modified).map_err(http::Error::from)?);
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if options.gzip || options.brotli {
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    if let Some(status) = check_preconditions(req, &etag, variant.modified) {
+        let mut res = Response::new(status);
+        if status == StatusCode::NOT_MODIFIED {
+            *res.headers_mut() = headers;
+        }
+        return Ok(res);
+    }
+
+    let content_type = match &options.content_type {
+        Some(content_type) => content_type.clone(),
+        None => guess_mime(path).to_string(),
+    };
+    if let Some(encoding) = variant.encoding {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+
+    let ranges = match req.headers().get(header::RANGE) {
+        Some(range) if if_range_matches(req, &etag, variant.modified) => {
+            parse_ranges(range.to_str().unwrap_or(""), variant.len)
+        }
+        _ => None,
+    };
+    let head = req.method() == Method::HEAD;
+
+    let mut res = match ranges {
+        None => {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::try_from(content_type).map_err(http::Error::from)?);
+            let mut res = Response::ok();
+            if !head {
+                res.set_file_body(FileRegion::new(variant.file, 0, variant.len));
+            }
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(variant.len));
+            res
+        }
+        Some(ranges) if ranges.is_empty() => {
+            headers.remove(header::CONTENT_ENCODING);
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::try_from(format!("bytes */{}", variant.len)).map_err(http::Error::from)?,
+            );
+            Response::new(StatusCode::RANGE_NOT_SATISFIABLE)
+        }
+        Some(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            headers.insert(header::CONTENT_TYPE, HeaderValue::try_from(content_type).map_err(http::Error::from)?);
+            headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::try_from(format!("bytes {}-{}/{}", start, end, variant.len)).map_err(http::Error::from)?,
+            );
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(end - start + 1));
+            let mut res = Response::new(StatusCode::PARTIAL_CONTENT);
+            if !head {
+                res.set_file_body(FileRegion::new(variant.file, start, end - start + 1));
+            }
+            res
+        }
+        Some(ranges) => {
+            // The parts are sent straight from the file between their
+            // headers, so nothing is read up front.
+            let boundary = format!("{:016x}", boundary_for(&etag));
+            let region = FileRegion::new(variant.file, 0, variant.len);
+            let mut parts = Vec::with_capacity(2 * ranges.len() + 1);
+            for (i, (start, end)) in ranges.into_iter().enumerate() {
+                parts.push(FilePart::Bytes(Bytes::from(format!(
+                    "{}--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    if i == 0 { "" } else { "\r\n" }, boundary, content_type, start, end, variant.len
+                ))));
+                parts.push(FilePart::Region(region.slice(start, end - start + 1)));
+            }
+            parts.push(FilePart::Bytes(Bytes::from(format!("\r\n--{}--\r\n", boundary))));
+
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::try_from(format!("multipart/byteranges; boundary={}", boundary)).map_err(http::Error::from)?,
+            );
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(parts.iter().map(FilePart::len).sum::<u64>()));
+            let mut res = Response::new(StatusCode::PARTIAL_CONTENT);
+            if !head {
+                res.set_file_parts(parts);
+            }
+            res
+        }
+    };
+
+    *res.headers_mut() = headers;
+    Ok(res)
+}
+
+fn etag(variant: &Variant) -> String {
+    let mtime = variant.modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    match variant.encoding {
+        Some(encoding) => format!("\"{:x}-{:x}-{}\"", mtime.as_nanos(), variant.len, encoding),
+        None => format!("\"{:x}-{:x}\"", mtime.as_nanos(), variant.len),
+    }
+}
+
+/// A boundary unlikely to occur in the file, derived from its validator.
+fn boundary_for(etag: &str) -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    etag.bytes().fold(nanos, |hash, b| hash.rotate_left(5) ^ b as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+fn header_str(req: &Request, name: header::HeaderName) -> Option<&str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+fn etag_list_matches(list: &str, etag: &str, weak: bool) -> bool {
+    let opaque = |tag: &str| tag.trim().strip_prefix("W/").unwrap_or(tag.trim()).to_string();
+    list.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        if candidate == "*" {
+            return true;
+        }
+        if !weak && candidate.starts_with("W/") {
+            return false;
+        }
+        opaque(candidate) == opaque(etag)
+    })
+}
+
+/// Whole seconds, the precision of HTTP dates.
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn not_after(modified: SystemTime, date: SystemTime) -> bool {
+    unix_secs(modified) <= unix_secs(date)
+}
+
+/// Evaluates the conditional headers in the order RFC 9110 prescribes,
+/// returning the status to answer with instead of the file.
+fn check_preconditions(req: &Request, etag: &str, modified: SystemTime) -> Option<StatusCode> {
+    if let Some(if_match) = header_str(req, header::IF_MATCH) {
+        if !etag_list_matches(if_match, etag, false) {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
+    } else if let Some(since) = header_str(req, header::IF_UNMODIFIED_SINCE).and_then(|v| httpdate::parse_http_date(v).ok())
+        && !not_after(modified, since)
+    {
+        return Some(StatusCode::PRECONDITION_FAILED);
+    }
+
+    if let Some(if_none_match) = header_str(req, header::IF_NONE_MATCH) {
+        if etag_list_matches(if_none_match, etag, true) {
+            return Some(StatusCode::NOT_MODIFIED);
+        }
+    } else if let Some(since) = header_str(req, header::IF_MODIFIED_SINCE).and_then(|v| httpdate::parse_http_date(v).ok())
+        && not_after(modified, since)
+    {
+        return Some(StatusCode::NOT_MODIFIED);
+    }
+    None
+}
+
+/// `If-Range` lets a client resume only if the file is unchanged.
+fn if_range_matches(req: &Request, etag: &str, modified: SystemTime) -> bool {
+    match header_str(req, header::IF_RANGE) {
+        None => true,
+        Some(value) if value.starts_with('"') => value == etag,
+        Some(value) => httpdate::parse_http_date(value).is_ok_and(|date| unix_secs(date) == unix_secs(modified)),
+    }
+}
+
+/// Parses a `bytes=` range header into inclusive `(start, end)` pairs.
+///
+/// `None` means the header is to be ignored (another unit, malformed or
+/// too many ranges); an empty list means nothing is satisfiable.
+fn parse_ranges(value: &str, len: u64) -> Option<Vec<(u64, u64)>> {
+    let specs = value.trim().strip_prefix("bytes=")?;
+    let mut ranges = Vec::new();
+    for spec in specs.split(',') {
+        let (start, end) = spec.trim().split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        let range = if start.is_empty() {
+            let suffix = end.parse::<u64>().ok()?;
+            (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+        } else {
+            let start = start.parse::<u64>().ok()?;
+            let end = if end.is_empty() { u64::MAX } else { end.parse::<u64>().ok()? };
+            if end < start {
+                return None;
+            }
+            (start < len).then(|| (start, end.min(len - 1)))
+        };
+        ranges.extend(range);
+    }
+    if ranges.len() > MAX_RANGES {
+        return None;
+    }
+    Some(ranges)
+}
+
+/// Media type by file extension, `application/octet-stream` if unknown.
+pub fn guess_mime(path: &Path) -> &'static str {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,255 @@
+mod store;
+
+pub use store::{SessionStore, SessionRecord, MemoryStore};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    http::{BodyMode, Cookie, Request, Response, SameSite, cookie_keys::random_token},
+    error::Result,
+    service::Service,
+};
+
+struct State {
+    data: HashMap<String, serde_json::Value>,
+    created: SystemTime,
+    regenerate: bool,
+    destroyed: bool,
+}
+
+/// Handle to the current request's session, available through
+/// `Request::session` behind a [`SessionService`].
+///
+/// Clones share the same data, so changes made by the handler are seen by
+/// the service when it persists the session.
+#[derive(Clone)]
+pub struct Session {
+    state: Rc<RefCell<State>>,
+}
+
+impl Session {
+    fn new(data: HashMap<String, serde_json::Value>, created: SystemTime) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                data,
+                created,
+                regenerate: false,
+                destroyed: false,
+            })),
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let state = self.state.borrow();
+        let value = state.data.get(key)?;
+        T::deserialize(value).ok()
+    }
+
+    pub fn insert<T: Serialize>(&self, key: &str, value: T) -> Result<()> {
+        let value = serde_json::to_value(value)?;
+        self.state.borrow_mut().data.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) -> bool {
+        self.state.borrow_mut().data.remove(key).is_some()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.state.borrow().data.contains_key(key)
+    }
+
+    pub fn clear(&self) {
+        self.state.borrow_mut().data.clear();
+    }
+
+    /// Moves the data to a fresh session ID once the handler returns.
+    ///
+    /// Call this whenever privileges change, e.g. on login, so an ID an
+    /// attacker planted beforehand becomes useless.
+    pub fn regenerate(&self) {
+        self.state.borrow_mut().regenerate = true;
+    }
+
+    /// Deletes the session from the store and the client.
+    pub fn destroy(&self) {
+        let mut state = self.state.borrow_mut();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+/// Settings for [`SessionService`].
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    cookie: Cookie,
+    idle_timeout: Duration,
+    absolute_timeout: Duration,
+    clock: fn() -> SystemTime,
+}
+
+impl SessionConfig {
+    pub fn new() -> Self {
+        Self {
+            cookie: Cookie::new("sark.sid", "")
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax),
+            idle_timeout: Duration::from_secs(30 * 60),
+            absolute_timeout: Duration::from_secs(24 * 60 * 60),
+            clock: SystemTime::now,
+        }
+    }
+
+    /// Template for the session cookie: its name and attributes are used,
+    /// its value is replaced by the session ID.
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// How long a session survives without requests.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// How long a session survives after creation, regardless of activity.
+    pub fn absolute_timeout(mut self, timeout: Duration) -> Self {
+        self.absolute_timeout = timeout;
+        self
+    }
+
+    /// Where expiry times are measured from, `SystemTime::now` by default.
+    pub fn clock(mut self, clock: fn() -> SystemTime) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads the session named by the request's cookie before calling the
+/// inner service and persists it afterwards.
+///
+/// Existing sessions are saved on every request to extend their idle
+/// timeout. New sessions are only stored, and their cookie only sent, once
+/// something has been inserted.
+pub struct SessionService<Svc, Store> {
+    inner: Svc,
+    store: Store,
+    config: SessionConfig,
+}
+
+impl<Svc, Store: SessionStore> SessionService<Svc, Store> {
+    pub fn new(inner: Svc, store: Store) -> Self {
+        Self {
+            inner,
+            store,
+            config: SessionConfig::default(),
+        }
+    }
+
+    pub fn config(mut self, config: SessionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    async fn load(&self, req: &Request, now: SystemTime) -> Result<(Option<String>, Session)> {
+        if let Some(id) = req.cookie(self.config.cookie.name()) {
+            match self.store.load(id).await? {
+                Some(record) if !record.is_expired(now) => {
+                    let created = record.created();
+                    return Ok((Some(id.to_string()), Session::new(record.into_data(), created)));
+                }
+                Some(_) => self.store.remove(id).await?,
+                None => (),
+            }
+        }
+        Ok((None, Session::new(HashMap::new(), now)))
+    }
+
+    async fn commit(&self, id: Option<String>, session: Session, resp: &mut Response, now: SystemTime) -> Result<()> {
+        let (data, created, regenerate, destroyed) = {
+            let mut state = session.state.borrow_mut();
+            (std::mem::take(&mut state.data), state.created, state.regenerate, state.destroyed)
+        };
+
+        if destroyed {
+            if let Some(id) = id {
+                self.store.remove(&id).await?;
+                resp.add_cookie(
+                    self.config.cookie.clone()
+                        .with_value(String::new())
+                        .max_age(Duration::ZERO)
+                        .expires(SystemTime::UNIX_EPOCH)
+                )?;
+            }
+            return Ok(());
+        }
+
+        let id = match id {
+            Some(old) if regenerate => {
+                self.store.remove(&old).await?;
+                None
+            }
+            id => id,
+        };
+        if id.is_none() && data.is_empty() {
+            return Ok(());
+        }
+
+        let expires = (now + self.config.idle_timeout).min(created + self.config.absolute_timeout);
+        let record = SessionRecord::new(data, created, now, expires);
+        match id {
+            Some(id) => self.store.store(&id, record).await?,
+            None => {
+                let id = random_token(32);
+                self.store.store(&id, record).await?;
+                resp.add_cookie(self.config.cookie.clone().with_value(id))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Svc> SessionService<Svc, MemoryStore> {
+    /// Drops every session that has expired by the configured clock.
+    pub fn purge_expired(&self) {
+        self.store.purge_expired((self.config.clock)());
+    }
+}
+
+impl<S, Svc, Store> Service<S> for SessionService<Svc, Store>
+where
+    Svc: Service<S>,
+    Store: SessionStore,
+{
+    async fn call(&self, mut req: Request, state: &S) -> Result<Response> {
+        let now = (self.config.clock)();
+        let (id, session) = self.load(&req, now).await?;
+
+        req.extensions_mut().insert(session.clone());
+        let mut resp = self.inner.call(req, state).await?;
+
+        self.commit(id, session, &mut resp, now).await?;
+        Ok(resp)
+    }
+
+    async fn before_body(&self, req: &Request, state: &S) -> Result<BodyMode> {
+        self.inner.before_body(req, state).await
+    }
+}
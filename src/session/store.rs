@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::error::Result;
+
+/// Persisted state of one session.
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    data: HashMap<String, serde_json::Value>,
+    created: SystemTime,
+    last_access: SystemTime,
+    expires: SystemTime,
+}
+
+impl SessionRecord {
+    pub fn new(data: HashMap<String, serde_json::Value>, created: SystemTime, last_access: SystemTime, expires: SystemTime) -> Self {
+        Self {
+            data,
+            created,
+            last_access,
+            expires,
+        }
+    }
+
+    pub fn data(&self) -> &HashMap<String, serde_json::Value> { &self.data }
+    pub fn into_data(self) -> HashMap<String, serde_json::Value> { self.data }
+    pub fn created(&self) -> SystemTime { self.created }
+    pub fn last_access(&self) -> SystemTime { self.last_access }
+
+    /// When the session stops being valid, whichever of the idle and
+    /// absolute timeouts comes first. Stores may use it as a TTL.
+    pub fn expires(&self) -> SystemTime { self.expires }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now >= self.expires
+    }
+}
+
+/// Backend that persists sessions by ID.
+pub trait SessionStore {
+    async fn load(&self, id: &str) -> Result<Option<SessionRecord>>;
+
+    async fn store(&self, id: &str, record: SessionRecord) -> Result<()>;
+
+    async fn remove(&self, id: &str) -> Result<()>;
+}
+
+/// Keeps sessions in the memory of the current thread.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: RefCell<HashMap<String, SessionRecord>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.borrow().is_empty()
+    }
+
+    /// Drops every session expired at `now`. Behind a [`SessionService`],
+    /// use `SessionService::purge_expired` so the configured clock is used.
+    ///
+    /// [`SessionService`]: super::SessionService
+    pub fn purge_expired(&self, now: SystemTime) {
+        self.sessions.borrow_mut().retain(|_, record| !record.is_expired(now));
+    }
+}
+
+impl SessionStore for MemoryStore {
+    async fn load(&self, id: &str) -> Result<Option<SessionRecord>> {
+        Ok(self.sessions.borrow().get(id).cloned())
+    }
+
+    async fn store(&self, id: &str, record: SessionRecord) -> Result<()> {
+        self.sessions.borrow_mut().insert(id.to_string(), record);
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> Result<()> {
+        self.sessions.borrow_mut().remove(id);
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use sark::{
+    error::Error,
+    http::{MultipartLimits, Request},
+};
+use http::{Method, Uri, HeaderValue};
+use serde::Deserialize;
+
+fn request_with_body(content_type: &'static str, body: &str) -> Request {
+    let mut req = Request::new(Method::POST, Uri::from_static("/upload"));
+    req.headers_mut().insert("content-type", HeaderValue::from_static(content_type));
+    req.set_body_str(body);
+    req
+}
+
+#[derive(Deserialize)]
+struct Login {
+    user: String,
+    remember: Option<bool>,
+}
+
+#[test]
+fn test_form() {
+    let req = request_with_body("application/x-www-form-urlencoded", "user=J%C3%BCrgen+S&remember=true");
+    let login: Login = req.form().unwrap();
+    assert_eq!(login.user, "J\u{fc}rgen S");
+    assert_eq!(login.remember, Some(true));
+
+    let req = request_with_body("application/json", "{}");
+    assert!(matches!(req.form::<Login>(), Err(Error::UnsupportedMediaType(_))));
+
+    let req = request_with_body("application/x-www-form-urlencoded", "remember=true");
+    assert!(matches!(req.form::<Login>(), Err(Error::Form(_))));
+}
+
+const MULTIPART_BODY: &str = "preamble\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\
+\r\n\
+Holiday\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"photo\"; filename=\"beach \\\"1\\\".jpg\"\r\n\
+Content-Type: image/jpeg\r\n\
+\r\n\
+\u{1}\u{2}binary\r\ndata\r\n\
+--XyZ--\r\n";
+
+#[monoio::test]
+async fn test_multipart() {
+    let mut req = request_with_body("multipart/form-data; boundary=\"XyZ\"", MULTIPART_BODY);
+    let mut multipart = req.multipart().unwrap();
+    let mut parts = Vec::new();
+    while let Some(part) = multipart.next_part().await.unwrap() {
+        parts.push(part);
+    }
+    assert_eq!(parts.len(), 2);
+
+    assert_eq!(parts[0].name(), Some("title"));
+    assert!(!parts[0].is_file());
+    assert_eq!(parts[0].text(), Some("Holiday"));
+
+    assert_eq!(parts[1].name(), Some("photo"));
+    assert_eq!(parts[1].filename(), Some("beach \"1\".jpg"));
+    assert_eq!(parts[1].content_type(), Some("image/jpeg"));
+    assert_eq!(parts[1].data(), b"\x01\x02binary\r\ndata");
+}
+
+#[monoio::test]
+async fn test_multipart_limits() {
+    let mut req = request_with_body("multipart/form-data; boundary=XyZ", MULTIPART_BODY);
+    let mut parts = req.multipart().unwrap().limits(MultipartLimits::new().part(8));
+    assert!(parts.next_part().await.unwrap().is_some());
+    assert!(matches!(parts.next_part().await, Err(Error::PayloadTooLarge(_))));
+    assert!(parts.next_part().await.unwrap().is_none());
+
+    let mut req = request_with_body("multipart/form-data; boundary=XyZ", MULTIPART_BODY);
+    let mut parts = req.multipart().unwrap().limits(MultipartLimits::new().total(10));
+    assert!(parts.next_part().await.unwrap().is_some());
+    assert!(matches!(parts.next_part().await, Err(Error::PayloadTooLarge(_))));
+
+    let mut req = request_with_body("multipart/form-data", MULTIPART_BODY);
+    assert!(matches!(req.multipart(), Err(Error::BadRequest(_))));
+}
@@ -0,0 +1,185 @@
+use sark::{
+    app::App,
+    compression::{CompressionConfig, CompressionService, DecompressionService, Encoding},
+    error::{Error, Result},
+    http::{Request, Response},
+    service::{Router, ServeDir, Service},
+};
+use http::{HeaderValue, Method, Uri};
+use std::io::{Read, Write};
+
+fn payload() -> String {
+    "the quick brown fox jumps over the lazy dog\n".repeat(100)
+}
+
+struct Page(&'static str);
+
+impl Service for Page {
+    async fn call(&self, _req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::builder()
+            .content_type(self.0)
+            .header("etag", "\"v1\"")
+            .body(payload().as_bytes())?;
+        if self.0 == "text/plain; no-transform" {
+            res.headers_mut().insert("content-type", HeaderValue::from_static("text/plain"));
+            res.headers_mut().insert("cache-control", HeaderValue::from_static("public, no-transform"));
+        }
+        Ok(res)
+    }
+}
+
+struct Small;
+
+impl Service for Small {
+    async fn call(&self, _req: Request, _state: &()) -> Result<Response> {
+        Ok(Response::text("tiny"))
+    }
+}
+
+fn compression_app(config: CompressionConfig) -> App<(), CompressionService<impl Service>> {
+    let router = Router::new()
+        .get("/text", Page("text/html; charset=utf-8"))
+        .get("/problem", Page("application/problem+json"))
+        .get("/image", Page("image/png"))
+        .get("/pinned", Page("text/plain; no-transform"))
+        .get("/small", Small);
+    App::with_empty_state(CompressionService::new(router).config(config))
+}
+
+async fn get<S: Service>(app: &App<(), S>, path: &'static str, accept: Option<&'static str>) -> Response {
+    let mut req = Request::new(Method::GET, Uri::from_static(path));
+    if let Some(accept) = accept {
+        req.headers_mut().insert("accept-encoding", HeaderValue::from_static(accept));
+    }
+    app.handle(req).await.ok().unwrap()
+}
+
+fn decode(encoding: &str, body: &[u8]) -> String {
+    let mut out = String::new();
+    match encoding {
+        "gzip" => flate2::read::GzDecoder::new(body).read_to_string(&mut out).unwrap(),
+        "deflate" => flate2::read::ZlibDecoder::new(body).read_to_string(&mut out).unwrap(),
+        "br" => brotli::Decompressor::new(body, 4096).read_to_string(&mut out).unwrap(),
+        other => panic!("unexpected encoding {}", other),
+    };
+    out
+}
+
+#[monoio::test]
+async fn test_negotiated_compression() {
+    let app = compression_app(CompressionConfig::new());
+
+    for (accept, expected) in [("gzip, br", "br"), ("gzip", "gzip"), ("deflate;q=1, br;q=0.5", "deflate")] {
+        let res = get(&app, "/text", Some(accept)).await;
+        assert_eq!(res.headers()["content-encoding"], expected, "{}", accept);
+        assert_eq!(res.headers()["vary"], "accept-encoding");
+        assert_eq!(res.headers()["etag"], "W/\"v1\"");
+        assert!(res.body().len() < payload().len());
+        assert_eq!(decode(expected, res.body()), payload());
+    }
+
+    let res = get(&app, "/problem", Some("gzip")).await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+
+    let res = get(&app, "/text", None).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    assert_eq!(res.body_str(), Some(payload().as_str()));
+
+    let res = get(&app, "/text", Some("identity, gzip;q=0")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+}
+
+#[monoio::test]
+async fn test_ineligible_responses_are_untouched() {
+    let app = compression_app(CompressionConfig::new());
+
+    let res = get(&app, "/image", Some("gzip")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    assert!(res.headers().get("vary").is_none());
+
+    let res = get(&app, "/pinned", Some("gzip")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+
+    let res = get(&app, "/small", Some("gzip")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+
+    let app = compression_app(CompressionConfig::new().min_size(1).encodings(&[Encoding::Gzip]));
+    let res = get(&app, "/small", Some("br")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    let res = get(&app, "/text", Some("br, gzip")).await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+}
+
+#[monoio::test]
+async fn test_file_bodies_are_compressed() {
+    let root = std::env::temp_dir().join(format!("sark-compress-files-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("page.css"), payload()).unwrap();
+    std::fs::write(root.join("large.txt"), payload().repeat(2)).unwrap();
+
+    let router = Router::new().get("/*path", ServeDir::new(&root));
+    let config = CompressionConfig::new().max_file_size(payload().len() as u64);
+    let app = App::with_empty_state(CompressionService::new(router).config(config));
+
+    let res = get(&app, "/page.css", Some("gzip")).await;
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert!(res.headers().get("content-length").is_none());
+    assert!(res.headers()["etag"].to_str().unwrap().starts_with("W/"));
+    assert!(res.file_body().is_none());
+    assert_eq!(decode("gzip", res.body()), payload());
+
+    let res = get(&app, "/large.txt", Some("gzip")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    assert_eq!(res.file_body().unwrap().len(), 2 * payload().len() as u64);
+}
+
+struct Echo;
+
+impl Service for Echo {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        assert!(req.headers().get("content-encoding").is_none());
+        Ok(Response::text(req.body_str().unwrap()))
+    }
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+async fn post(app: &App<(), DecompressionService<Echo>>, encoding: &'static str, body: Vec<u8>) -> Result<Response> {
+    let mut req = Request::new(Method::POST, Uri::from_static("/"));
+    req.headers_mut().insert("content-encoding", HeaderValue::from_static(encoding));
+    req.set_body(&body[..]);
+    app.handle(req).await
+}
+
+#[monoio::test]
+async fn test_request_decompression() {
+    let app = App::with_empty_state(DecompressionService::new(Echo).max_size(4096));
+    let json = br#"{"events":[1,2,3]}"#;
+
+    let res = post(&app, "gzip", gzip(json)).await.ok().unwrap();
+    assert_eq!(res.body(), &json[..]);
+
+    let mut deflated = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    deflated.write_all(&gzip(json)).unwrap();
+    let res = post(&app, "gzip, deflate", deflated.finish().unwrap()).await.ok().unwrap();
+    assert_eq!(res.body(), &json[..]);
+
+    let res = post(&app, "identity", json.to_vec()).await.ok().unwrap();
+    assert_eq!(res.body(), &json[..]);
+
+    assert!(matches!(post(&app, "zstd", json.to_vec()).await, Err(Error::UnsupportedMediaType(_))));
+    assert!(matches!(post(&app, "gzip", json.to_vec()).await, Err(Error::BadRequest(_))));
+
+    let bomb = gzip(&vec![b'a'; 1024 * 1024]);
+    assert!(bomb.len() < 4096);
+    let err = post(&app, "gzip", bomb).await.err().unwrap();
+    assert!(matches!(err, Error::PayloadTooLarge(_)));
+    assert_eq!(err.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+}
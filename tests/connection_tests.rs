@@ -0,0 +1,69 @@
+use sark::http::{ForwardedHeader, TrustedProxies};
+use http::{HeaderMap, HeaderValue};
+use std::net::IpAddr;
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        map.append(*name, HeaderValue::from_static(value));
+    }
+    map
+}
+
+#[test]
+fn test_untrusted_peer_is_the_client() {
+    let proxies = TrustedProxies::new().trust("10.0.0.0/8").unwrap();
+    let spoofed = headers(&[("x-forwarded-for", "1.2.3.4")]);
+    assert_eq!(proxies.client_ip(ip("203.0.113.9"), &spoofed), ip("203.0.113.9"));
+    assert_eq!(TrustedProxies::new().client_ip(ip("10.0.0.1"), &spoofed), ip("10.0.0.1"));
+}
+
+#[test]
+fn test_x_forwarded_for_chain() {
+    let proxies = TrustedProxies::new()
+        .trust("10.0.0.0/8").unwrap()
+        .trust("2001:db8::/32").unwrap();
+
+    let chain = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.7"), ("x-forwarded-for", "10.1.2.3")]);
+    assert_eq!(proxies.client_ip(ip("10.0.0.1"), &chain), ip("198.51.100.7"));
+
+    let all_trusted = headers(&[("x-forwarded-for", "10.9.9.9, 10.1.2.3")]);
+    assert_eq!(proxies.client_ip(ip("::ffff:10.0.0.1"), &all_trusted), ip("10.9.9.9"));
+
+    let garbage = headers(&[("x-forwarded-for", "198.51.100.7, unknown")]);
+    assert_eq!(proxies.client_ip(ip("2001:db8::1"), &garbage), ip("2001:db8::1"));
+}
+
+#[test]
+fn test_forwarded_header() {
+    let proxies = TrustedProxies::new()
+        .trust("192.0.2.0/24").unwrap()
+        .header(ForwardedHeader::Forwarded);
+    let forwarded = headers(&[
+        ("forwarded", "for=\"[2001:db8:cafe::17]:4711\";proto=https, for=192.0.2.43:80;by=192.0.2.1"),
+        ("x-forwarded-for", "6.6.6.6"),
+    ]);
+    assert_eq!(proxies.client_ip(ip("192.0.2.1"), &forwarded), ip("2001:db8:cafe::17"));
+
+    // An element without `for` is still a hop, so the walk stops there.
+    let anonymous_hop = headers(&[("forwarded", "for=198.51.100.7, by=192.0.2.9")]);
+    assert_eq!(proxies.client_ip(ip("192.0.2.1"), &anonymous_hop), ip("192.0.2.1"));
+    assert!(TrustedProxies::new().trust("10.0.0.0/33").is_err());
+}
+
+#[test]
+fn test_client_cannot_switch_headers() {
+    // The proxy appends the peer to X-Forwarded-For; a Forwarded header can
+    // only have come from the client.
+    let proxies = TrustedProxies::new().trust("10.0.0.0/8").unwrap();
+    let spoofed = headers(&[("forwarded", "for=1.2.3.4"), ("x-forwarded-for", "198.51.100.7")]);
+    assert_eq!(proxies.client_ip(ip("10.0.0.1"), &spoofed), ip("198.51.100.7"));
+
+    let proxies = proxies.header(ForwardedHeader::Forwarded);
+    let spoofed = headers(&[("x-forwarded-for", "1.2.3.4"), ("forwarded", "for=198.51.100.7")]);
+    assert_eq!(proxies.client_ip(ip("10.0.0.1"), &spoofed), ip("198.51.100.7"));
+}
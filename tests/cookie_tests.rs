@@ -0,0 +1,151 @@
+use sark::{
+    app::App,
+    error::{Error, Result},
+    http::{Cookie, CookieKeys, Key, Request, Response, SameSite},
+    service::{Router, Service},
+};
+use http::{Method, Uri, HeaderValue};
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_request_cookies() {
+    let mut req = Request::new(Method::GET, Uri::from_static("/"));
+    req.headers_mut().append("cookie", HeaderValue::from_static("session=abc123; theme=\"dark\""));
+    req.headers_mut().append("cookie", HeaderValue::from_static("lang=en;theme=light"));
+
+    assert_eq!(req.cookie("session"), Some("abc123"));
+    assert_eq!(req.cookie("theme"), Some("dark"));
+    assert_eq!(req.cookie("lang"), Some("en"));
+    assert_eq!(req.cookie("missing"), None);
+    assert_eq!(req.cookies().count(), 4);
+}
+
+#[test]
+fn test_set_cookie_headers() {
+    let mut resp = Response::ok();
+    resp.add_cookie(
+        Cookie::new("session", "abc123")
+            .path("/")
+            .domain("example.com")
+            .max_age(Duration::from_secs(3600))
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax)
+    ).unwrap();
+    resp.add_cookie(
+        Cookie::new("seen", "1").expires(SystemTime::UNIX_EPOCH + Duration::from_secs(784111777))
+    ).unwrap();
+    resp.add_cookie(Cookie::removal("old")).unwrap();
+
+    let values: Vec<_> = resp.headers().get_all("set-cookie").iter()
+        .map(|v| v.to_str().unwrap())
+        .collect();
+    assert_eq!(values, vec![
+        "session=abc123; Path=/; Domain=example.com; Max-Age=3600; Secure; HttpOnly; SameSite=Lax",
+        "seen=1; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+        "old=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+    ]);
+
+    assert!(resp.add_cookie(Cookie::new("bad", "line\nbreak")).is_err());
+}
+
+#[test]
+fn test_cookie_validation() {
+    let mut resp = Response::ok();
+    assert!(matches!(resp.add_cookie(Cookie::new("theme", "dark; Domain=evil.example")), Err(Error::InvalidCookie(_))));
+    assert!(matches!(resp.add_cookie(Cookie::new("the me", "dark")), Err(Error::InvalidCookie(_))));
+    assert!(matches!(resp.add_cookie(Cookie::new("", "dark")), Err(Error::InvalidCookie(_))));
+    assert!(matches!(resp.add_cookie(Cookie::new("theme", "a b")), Err(Error::InvalidCookie(_))));
+    assert!(matches!(resp.add_cookie(Cookie::new("theme", "dark").path("/; Secure")), Err(Error::InvalidCookie(_))));
+    assert!(matches!(resp.add_cookie(Cookie::new("theme", "dark").domain("a.example;")), Err(Error::InvalidCookie(_))));
+    assert!(resp.headers().get("set-cookie").is_none());
+
+    resp.add_cookie(Cookie::new("theme", "\"dark\"")).unwrap();
+    assert_eq!(resp.headers()["set-cookie"], "theme=\"dark\"");
+}
+
+fn echo_cookie(resp: &Response) -> Request {
+    let set_cookie = resp.headers().get("set-cookie").unwrap().to_str().unwrap();
+    let pair = set_cookie.split(';').next().unwrap().to_string();
+    let mut req = Request::new(Method::GET, Uri::from_static("/"));
+    req.headers_mut().insert("cookie", HeaderValue::try_from(pair).unwrap());
+    req
+}
+
+#[test]
+fn test_signed_cookie() {
+    let keys = CookieKeys::new(Key::generate());
+    let mut resp = Response::ok();
+    resp.add_signed_cookie(Cookie::new("user", "42").http_only(true), &keys).unwrap();
+
+    let req = echo_cookie(&resp);
+    let raw = req.cookie("user").unwrap();
+    assert!(raw.ends_with(".42"));
+    assert_eq!(req.signed_cookie("user", &keys), Some("42".to_string()));
+
+    let mut forged = Request::new(Method::GET, Uri::from_static("/"));
+    let tampered = format!("user={}", raw.replace(".42", ".1"));
+    forged.headers_mut().insert("cookie", HeaderValue::try_from(tampered).unwrap());
+    assert_eq!(forged.signed_cookie("user", &keys), None);
+    assert_eq!(req.signed_cookie("user", &CookieKeys::new(Key::generate())), None);
+}
+
+#[test]
+fn test_private_cookie_with_rotation() {
+    let old = Key::from_bytes(&[7u8; 32]).unwrap();
+    let keys = CookieKeys::new(old.clone());
+    let mut resp = Response::ok();
+    resp.add_private_cookie(Cookie::new("session", "secret data"), &keys).unwrap();
+
+    let req = echo_cookie(&resp);
+    assert!(!req.cookie("session").unwrap().contains("secret"));
+    assert_eq!(req.private_cookie("session", &keys), Some("secret data".to_string()));
+
+    let rotated = CookieKeys::new(Key::generate()).with_previous(old);
+    assert_eq!(req.private_cookie("session", &rotated), Some("secret data".to_string()));
+    assert_eq!(req.private_cookie("session", &CookieKeys::new(Key::generate())), None);
+    assert_eq!(req.signed_cookie("session", &keys), None);
+
+    assert!(Key::from_bytes(b"short").is_err());
+}
+
+struct Visits;
+
+impl Service for Visits {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let mut jar = req.cookie_jar()?;
+        let visits: u32 = jar.private("visits").and_then(|v| v.parse().ok()).unwrap_or(0) + 1;
+        jar.add_private(Cookie::new("visits", visits.to_string()).http_only(true))?;
+        assert_eq!(jar.private("visits"), Some(visits.to_string()));
+        if visits > 1 {
+            jar.remove(Cookie::new("greeted", "").path("/"))?;
+            assert_eq!(jar.get("greeted"), None);
+        }
+
+        let mut resp = Response::text(visits.to_string());
+        resp.add_cookies(&jar)?;
+        Ok(resp)
+    }
+}
+
+#[monoio::test]
+async fn test_cookie_jar() {
+    let keys = CookieKeys::new(Key::generate());
+    let app = App::with_empty_state(Router::new().get("/", Visits)).cookie_keys(keys);
+
+    let resp = app.handle(Request::new(Method::GET, Uri::from_static("/"))).await.ok().unwrap();
+    assert_eq!(resp.body_str(), Some("1"));
+    let mut req = echo_cookie(&resp);
+    assert!(!req.cookie("visits").unwrap().starts_with('1'));
+    req.headers_mut().append("cookie", HeaderValue::from_static("greeted=yes"));
+
+    let resp = app.handle(req).await.ok().unwrap();
+    assert_eq!(resp.body_str(), Some("2"));
+    let values: Vec<_> = resp.headers().get_all("set-cookie").iter().map(|v| v.to_str().unwrap()).collect();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[1], "greeted=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+
+    let without_keys = App::with_empty_state(Router::new().get("/", Visits));
+    let err = without_keys.handle(Request::new(Method::GET, Uri::from_static("/"))).await.err().unwrap();
+    assert!(matches!(err, Error::Internal(_)));
+}
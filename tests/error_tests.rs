@@ -0,0 +1,187 @@
+use sark::{
+    app::App,
+    error::{Error, HttpError, ResponseError, Result},
+    http::{Problem, Request, Response},
+    service::{FnHandler, Service},
+};
+use http::{Method, StatusCode, Uri};
+use serde_json::{json, Value};
+use std::error::Error as _;
+
+#[derive(Debug, thiserror::Error)]
+enum AccountError {
+    #[error("account {0} is locked")]
+    Locked(u32),
+    #[error("storage unavailable")]
+    Storage(#[source] std::io::Error),
+}
+
+impl ResponseError for AccountError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AccountError::Locked(_) => StatusCode::FORBIDDEN,
+            AccountError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn code(&self) -> Option<&str> {
+        match self {
+            AccountError::Locked(_) => Some("account_locked"),
+            AccountError::Storage(_) => None,
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            AccountError::Locked(id) => Some(json!({ "account": id })),
+            AccountError::Storage(_) => None,
+        }
+    }
+}
+
+struct Account;
+
+impl Service for Account {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        match req.path_param("id") {
+            Some("1") => Err(AccountError::Locked(1))?,
+            Some("2") => Err(AccountError::Storage(std::io::Error::other("disk on fire")))?,
+            _ => Err(HttpError::new(StatusCode::CONFLICT, "already exists")
+                .code("duplicate")
+                .details(json!(["name"]))
+                .source(std::fmt::Error))?,
+        }
+    }
+}
+
+async fn get(path: &'static str) -> Error {
+    let app = App::default().get("/accounts/:id", Account);
+    match app.handle(Request::new(Method::GET, Uri::from_static(path))).await {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    }
+}
+
+fn json_body(res: &Response) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[monoio::test]
+async fn test_custom_error_keeps_structure() {
+    let err = get("/accounts/1").await;
+    assert!(matches!(err.downcast_ref::<AccountError>(), Some(AccountError::Locked(1))));
+    assert_eq!(err.status(), StatusCode::FORBIDDEN);
+
+    let res = err.render(false);
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(res.headers()["content-type"], "application/problem+json");
+    assert_eq!(
+        json_body(&res),
+        json!({
+            "title": "Forbidden",
+            "status": 403,
+            "detail": "account 1 is locked",
+            "code": "account_locked",
+            "details": { "account": 1 },
+        })
+    );
+}
+
+#[monoio::test]
+async fn test_custom_error_source_chain() {
+    let err = get("/accounts/2").await;
+    assert_eq!(err.to_string(), "storage unavailable");
+    assert_eq!(err.source().unwrap().to_string(), "disk on fire");
+
+    let res = err.render(false);
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json_body(&res), json!({ "title": "Service Unavailable", "status": 503 }));
+    assert_eq!(json_body(&err.render(true))["detail"], "storage unavailable");
+
+    let err = get("/accounts/3").await;
+    assert!(err.source().is_some());
+    assert_eq!(
+        json_body(&err.render(false)),
+        json!({ "title": "Conflict", "status": 409, "detail": "already exists", "code": "duplicate", "details": ["name"] })
+    );
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("maintenance until {0}")]
+struct Maintenance(&'static str);
+
+impl ResponseError for Maintenance {
+    fn status(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn render(&self) -> Option<Response> {
+        let mut res = Response::text(format!("back at {}", self.0));
+        res.set_status(self.status());
+        res.headers_mut().insert("retry-after", http::HeaderValue::from_static("600"));
+        Some(res)
+    }
+}
+
+#[test]
+fn test_custom_render_for_server_errors() {
+    let err = Error::app(Maintenance("noon"));
+    for expose_details in [false, true] {
+        let res = err.render(expose_details);
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()["retry-after"], "600");
+        assert_eq!(res.body_str(), Some("back at noon"));
+    }
+}
+
+#[test]
+fn test_builtin_error_rendering() {
+    let res = Error::NotFound.render(false);
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(json_body(&res), json!({ "title": "Not Found", "status": 404, "detail": "Not found" }));
+
+    let err = Error::Internal("db password is hunter2".into());
+    let res = err.render(false);
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(json_body(&res), json!({ "title": "Internal Server Error", "status": 500 }));
+    assert_eq!(json_body(&err.render(true))["detail"], "Internal error: db password is hunter2");
+}
+
+#[monoio::test]
+async fn test_problem_from_handlers() {
+    let problem = Problem::new(StatusCode::PAYMENT_REQUIRED)
+        .type_uri("https://example.com/probs/out-of-credit")
+        .title("You do not have enough credit.")
+        .detail("Your current balance is 30, but that costs 50.")
+        .instance("/account/12345/msgs/abc")
+        .extension("balance", 30)
+        .extension("status", "ignored");
+    let expected = json!({
+        "type": "https://example.com/probs/out-of-credit",
+        "title": "You do not have enough credit.",
+        "status": 402,
+        "detail": "Your current balance is 30, but that costs 50.",
+        "instance": "/account/12345/msgs/abc",
+        "balance": 30,
+    });
+
+    let returned = problem.clone();
+    let raised = problem.clone();
+    let app = App::default()
+        .get("/returned", FnHandler::new(async move |_req: Request, _state: &()| returned.clone()))
+        .get("/raised", FnHandler::new(async move |_req: Request, _state: &()| -> Result<Response> {
+            Err(raised.clone())?
+        }));
+
+    for uri in ["/returned", "/raised"] {
+        let err = match app.handle(Request::new(Method::GET, Uri::from_static(uri))).await {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err,
+        };
+        assert_eq!(err.downcast_ref::<Problem>(), Some(&problem));
+        let res = err.render(false);
+        assert_eq!(res.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(res.headers()["content-type"], "application/problem+json");
+        assert_eq!(json_body(&res), expected);
+    }
+}
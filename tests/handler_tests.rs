@@ -0,0 +1,111 @@
+use sark::{
+    app::App,
+    error::{Error, ResponseError, Result},
+    http::{IntoResponse, Json, Request, Response},
+    service::{FnHandler, Service},
+};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct User {
+    id: u32,
+    name: &'static str,
+}
+
+#[derive(Debug)]
+enum ApiError {
+    NoSuchUser(u32),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Result<Response> {
+        match self {
+            ApiError::NoSuchUser(id) => (StatusCode::NOT_FOUND, format!("no user {}", id)).into_response(),
+        }
+    }
+}
+
+async fn user(req: Request, _state: &()) -> std::result::Result<Json<User>, ApiError> {
+    match req.path_param("id") {
+        Some("1") => Ok(Json(User { id: 1, name: "ada" })),
+        other => Err(ApiError::NoSuchUser(other.unwrap_or("0").parse().unwrap_or(0))),
+    }
+}
+
+#[derive(Debug)]
+struct OutOfStock(&'static str);
+
+impl std::fmt::Display for OutOfStock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is out of stock", self.0)
+    }
+}
+
+impl std::error::Error for OutOfStock {}
+
+impl ResponseError for OutOfStock {
+    fn status(&self) -> StatusCode {
+        StatusCode::CONFLICT
+    }
+}
+
+async fn order(req: Request, _state: &()) -> std::result::Result<Json<&'static str>, OutOfStock> {
+    match req.path_param("item") {
+        Some("tea") => Ok(Json("ordered")),
+        _ => Err(OutOfStock("coffee")),
+    }
+}
+
+async fn get(app: &App<(), impl Service>, path: &'static str) -> Result<Response> {
+    app.handle(Request::new(Method::GET, Uri::from_static(path))).await
+}
+
+#[monoio::test]
+async fn test_fn_handlers_return_into_response() {
+    let app = App::default()
+        .get("/users/:id", FnHandler::new(user))
+        .get("/text", FnHandler::new(async |_req: Request, _state: &()| "hello"))
+        .get("/created", FnHandler::new(async |_req: Request, _state: &()| {
+            let mut headers = HeaderMap::new();
+            headers.insert("location", HeaderValue::from_static("/users/2"));
+            (StatusCode::CREATED, headers, Bytes::from_static(b"\x00\x01"))
+        }))
+        .get("/fail", FnHandler::new(async |_req: Request, _state: &()| {
+            Err::<String, _>(Error::Forbidden("nope".into()))
+        }));
+
+    let res = get(&app, "/users/1").await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(res.body_str(), Some(r#"{"id":1,"name":"ada"}"#));
+
+    let res = get(&app, "/users/9").await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.body_str(), Some("no user 9"));
+
+    let res = get(&app, "/text").await.unwrap();
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.body_str(), Some("hello"));
+
+    let res = get(&app, "/created").await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["location"], "/users/2");
+    assert_eq!(res.headers()["content-type"], "application/octet-stream");
+    assert_eq!(&res.body()[..], b"\x00\x01");
+
+    assert!(matches!(get(&app, "/fail").await, Err(Error::Forbidden(_))));
+}
+
+#[monoio::test]
+async fn test_fn_handlers_return_response_errors() {
+    let app = App::default().get("/orders/:item", FnHandler::new(order));
+
+    let res = get(&app, "/orders/tea").await.ok().unwrap();
+    assert_eq!(res.body_str(), Some(r#""ordered""#));
+
+    let err = get(&app, "/orders/coffee").await.err().unwrap();
+    assert_eq!(err.status(), StatusCode::CONFLICT);
+    assert!(err.downcast_ref::<OutOfStock>().is_some());
+    assert_eq!(err.render(false).status(), StatusCode::CONFLICT);
+}
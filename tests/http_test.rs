@@ -1,5 +1,6 @@
-use sark::http::{Request, Response};
-use http::{Method, StatusCode, HeaderValue};
+use sark::{error::Error, http::{Request, Response}};
+use http::{Method, StatusCode, HeaderValue, Uri};
+use serde::Deserialize;
 
 #[monoio::test]
 async fn test_request_creation() {
@@ -30,4 +31,166 @@ async fn test_response_creation() {
     resp.headers_mut().insert("content-type", HeaderValue::from_static("text/plain"));
     
     assert_eq!(resp.headers().get("content-type").unwrap(), "text/plain");
-}
\ No newline at end of file
+}
+
+#[monoio::test]
+async fn test_extensions() {
+    use sark::{error::Result, service::Service};
+    use std::rc::Rc;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct CurrentUser(Rc<str>);
+
+    struct Auth<S>(S);
+
+    impl<S: Service> Service for Auth<S> {
+        async fn call(&self, mut req: Request, state: &()) -> Result<Response> {
+            req.extensions_mut().insert(CurrentUser(Rc::from("alice")));
+            let mut resp = self.0.call(req, state).await?;
+            resp.extensions_mut().insert(7u32);
+            Ok(resp)
+        }
+    }
+
+    struct Handler;
+
+    impl Service for Handler {
+        async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+            let user = req.extensions().get::<CurrentUser>().unwrap();
+            let mut resp = Response::ok();
+            resp.set_body_str(&user.0);
+            Ok(resp)
+        }
+    }
+
+    let resp = Auth(Handler).call(Request::default(), &()).await.unwrap();
+    assert_eq!(resp.body_str(), Some("alice"));
+    assert_eq!(resp.extensions().get::<u32>(), Some(&7));
+
+    let mut req = Request::default();
+    assert_eq!(req.extensions_mut().insert(1u8), None);
+    assert_eq!(req.extensions_mut().insert(2u8), Some(1));
+    *req.extensions_mut().get_mut::<u8>().unwrap() += 1;
+    let cloned = req.clone();
+    assert_eq!(req.extensions_mut().remove::<u8>(), Some(3));
+    assert!(!req.extensions().contains::<u8>());
+    assert_eq!(cloned.extensions().get::<u8>(), Some(&3));
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Search {
+    q: String,
+    page: Option<u32>,
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+#[test]
+fn test_query_as() {
+    let req = Request::new(Method::GET, Uri::from_static("/search?q=rust+lang&tag=a&page=2&tag=b%20c"));
+    let search: Search = req.query_as().unwrap();
+    assert_eq!(search, Search { q: "rust lang".into(), page: Some(2), tag: vec!["a".into(), "b c".into()] });
+    assert_eq!(req.query_all("tag"), vec!["a", "b c"]);
+    assert_eq!(req.query("tag").as_deref(), Some("b c"));
+
+    let req = Request::new(Method::GET, Uri::from_static("/search?q=x&tag=only"));
+    let search: Search = req.query_as().unwrap();
+    assert_eq!(search.tag, vec!["only".to_string()]);
+    assert_eq!(search.page, None);
+
+    let req = Request::new(Method::GET, Uri::from_static("/search?q=x&page=two"));
+    assert!(matches!(req.query_as::<Search>(), Err(Error::InvalidQuery(msg)) if msg.contains("two")));
+    let req = Request::new(Method::GET, Uri::from_static("/search?page=1"));
+    assert!(matches!(req.query_as::<Search>(), Err(Error::InvalidQuery(_))));
+
+    let mut req = Request::new(Method::GET, Uri::from_static("/search?q=old"));
+    assert_eq!(req.query("q").as_deref(), Some("old"));
+    req.with_uri(Uri::from_static("/search?q=new"));
+    assert_eq!(req.query("q").as_deref(), Some("new"));
+}
+
+fn with_header(name: &'static str, value: &'static str) -> Request {
+    let mut req = Request::new(Method::GET, Uri::from_static("/report"));
+    req.headers_mut().insert(name, HeaderValue::from_static(value));
+    req
+}
+
+#[test]
+fn test_negotiate() {
+    let offered = ["application/json", "text/csv"];
+    assert_eq!(Request::default().negotiate(&offered).unwrap(), "application/json");
+
+    let req = with_header("accept", "text/csv;q=0.9, application/json;q=0.5, */*;q=0.1");
+    assert_eq!(req.negotiate(&offered).unwrap(), "text/csv");
+    assert_eq!(req.accept()[0].value(), "text/csv");
+
+    let req = with_header("accept", "text/*, application/json;q=0.8");
+    assert_eq!(req.negotiate(&offered).unwrap(), "text/csv");
+
+    let req = with_header("accept", "*/*, text/csv;q=0");
+    assert_eq!(req.negotiate(&["text/csv"]).ok(), None);
+    assert!(matches!(req.negotiate(&["text/csv"]), Err(Error::NotAcceptable(_))));
+    assert_eq!(req.negotiate(&offered).unwrap(), "application/json");
+
+    let req = with_header("accept", "image/png");
+    assert!(matches!(req.negotiate(&offered), Err(Error::NotAcceptable(_))));
+}
+
+#[test]
+fn test_negotiate_language_encoding_charset() {
+    let req = with_header("accept-language", "fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5");
+    assert_eq!(req.negotiate_language(&["en-US", "fr"]).unwrap(), "fr");
+    assert_eq!(req.negotiate_language(&["en-GB", "de"]).unwrap(), "en-GB");
+
+    let req = with_header("accept-encoding", "gzip;q=0.8, br");
+    assert_eq!(req.negotiate_encoding(&["gzip", "br", "identity"]).unwrap(), "br");
+    assert_eq!(req.negotiate_encoding(&["deflate", "identity"]).unwrap(), "identity");
+
+    let req = with_header("accept-encoding", "*;q=0");
+    assert!(req.negotiate_encoding(&["gzip", "identity"]).is_err());
+
+    let req = with_header("accept-charset", "iso-8859-1, utf-8;q=0.7");
+    assert_eq!(req.negotiate_charset(&["UTF-8", "ISO-8859-1"]).unwrap(), "ISO-8859-1");
+    assert!(req.negotiate_charset(&["utf-16"]).is_err());
+}
+
+#[test]
+fn test_response_builder() {
+    let res = Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header("x-trace", "a")
+        .header("x-trace", "b")
+        .content_type("text/csv")
+        .body("id,name\n")
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(res.headers().get_all("x-trace").iter().count(), 2);
+    assert_eq!(res.headers()["content-type"], "text/csv");
+    assert_eq!(res.body_str(), Some("id,name\n"));
+
+    assert!(Response::builder().header("bad header", "x").build().is_err());
+    assert!(Response::builder().header("x-ok", "line\nbreak").body("ignored").is_err());
+}
+
+#[test]
+fn test_response_constructors() {
+    let res = Response::created("/users/7").unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["location"], "/users/7");
+
+    assert_eq!(Response::permanent_redirect("/new").unwrap().status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(Response::temporary_redirect("/new").unwrap().status(), StatusCode::TEMPORARY_REDIRECT);
+    let res = Response::see_other("/done").unwrap();
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers()["location"], "/done");
+    assert!(Response::see_other("/\r\n").is_err());
+
+    let res = Response::no_content();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(res.body().is_empty());
+
+    let res = Response::text("hi");
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.body_str(), Some("hi"));
+    assert_eq!(Response::html("<p>hi</p>").headers()["content-type"], "text/html; charset=utf-8");
+}
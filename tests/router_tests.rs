@@ -0,0 +1,277 @@
+use sark::{
+    app::App,
+    error::{Error, Result},
+    http::{Request, Response},
+    service::{Service, Router, HostRouter, PathPolicy, get, normalize_path},
+};
+use http::{Method, Uri, HeaderValue, StatusCode};
+
+struct Text(&'static str);
+
+impl Service for Text {
+    async fn call(&self, _req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        res.set_body_str(self.0);
+        Ok(res)
+    }
+}
+
+struct EchoParam(&'static str);
+
+impl Service for EchoParam {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        res.set_body_str(req.path_param(self.0).unwrap_or(""));
+        Ok(res)
+    }
+}
+
+async fn body_of<S: Service>(app: &App<(), S>, method: Method, path: &'static str) -> Result<String> {
+    let req = Request::new(method, Uri::from_static(path));
+    let res = app.handle(req).await?;
+    Ok(res.body_str().unwrap().to_string())
+}
+
+#[monoio::test]
+async fn test_static_beats_param_regardless_of_order() {
+    let app = App::default()
+        .route(Method::GET, "/users/me", Text("me"))
+        .route(Method::GET, "/users/:id", EchoParam("id"));
+    assert_eq!(body_of(&app, Method::GET, "/users/me").await.unwrap(), "me");
+    assert_eq!(body_of(&app, Method::GET, "/users/42").await.unwrap(), "42");
+
+    let app = App::default()
+        .route(Method::GET, "/users/:id", EchoParam("id"))
+        .route(Method::GET, "/users/me", Text("me"));
+    assert_eq!(body_of(&app, Method::GET, "/users/me").await.unwrap(), "me");
+    assert_eq!(body_of(&app, Method::GET, "/users/42").await.unwrap(), "42");
+}
+
+#[monoio::test]
+async fn test_param_beats_wildcard() {
+    let app = App::default()
+        .route(Method::GET, "/files/*path", EchoParam("path"))
+        .route(Method::GET, "/files/:name", Text("single"));
+    assert_eq!(body_of(&app, Method::GET, "/files/a.txt").await.unwrap(), "single");
+    assert_eq!(body_of(&app, Method::GET, "/files/a/b.txt").await.unwrap(), "a/b.txt");
+    assert!(matches!(body_of(&app, Method::POST, "/files/a.txt").await, Err(Error::MethodNotAllowed(m)) if m == [Method::GET]));
+}
+
+#[monoio::test]
+async fn test_validate_reports_shadowed_route() {
+    let app = App::default()
+        .route(Method::GET, "/users/:id", EchoParam("id"))
+        .route(Method::POST, "/users/:id", EchoParam("id"))
+        .route(Method::GET, "/users/me", Text("me"));
+    assert!(app.validate().is_ok());
+
+    let app = App::default()
+        .route(Method::GET, "/users/:id", EchoParam("id"))
+        .route(Method::GET, "/users/:name", EchoParam("name"));
+    match app.validate() {
+        Err(Error::RouteConflict(msg)) => {
+            assert_eq!(msg, "GET /users/:name is unreachable, it is shadowed by GET /users/:id");
+        }
+        _ => panic!("expected a route conflict"),
+    }
+    assert_eq!(body_of(&app, Method::GET, "/users/7").await.unwrap(), "7");
+}
+
+#[test]
+#[should_panic(expected = "wildcard must be the last segment")]
+fn test_wildcard_must_be_last() {
+    let _ = App::default().route(Method::GET, "/files/*path/raw", Text("raw"));
+}
+
+#[monoio::test]
+async fn test_method_router_dispatch() {
+    let app = App::default()
+        .at("/users/:id", get(EchoParam("id")).put(Text("updated")).delete(Text("deleted")))
+        .post("/users", Text("created"));
+    assert_eq!(body_of(&app, Method::GET, "/users/5").await.unwrap(), "5");
+    assert_eq!(body_of(&app, Method::PUT, "/users/5").await.unwrap(), "updated");
+    assert_eq!(body_of(&app, Method::DELETE, "/users/5").await.unwrap(), "deleted");
+    assert_eq!(body_of(&app, Method::POST, "/users").await.unwrap(), "created");
+    let err = body_of(&app, Method::PATCH, "/users/5").await.unwrap_err();
+    assert!(matches!(&err, Error::MethodNotAllowed(m) if *m == [Method::GET, Method::PUT, Method::DELETE]));
+    let res = err.render(false);
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers()["allow"], "GET, PUT, DELETE");
+    assert!(matches!(body_of(&app, Method::GET, "/posts/5").await, Err(Error::NotFound)));
+}
+
+#[monoio::test]
+async fn test_method_falls_through_to_less_specific_route() {
+    let app = App::default()
+        .get("/users/me", Text("me"))
+        .delete("/users/:id", EchoParam("id"));
+    assert_eq!(body_of(&app, Method::DELETE, "/users/me").await.unwrap(), "me");
+    let err = body_of(&app, Method::PUT, "/users/me").await.unwrap_err();
+    assert_eq!(err.render(false).headers()["allow"], "GET, DELETE");
+}
+
+#[test]
+fn test_validate_checks_method_routers() {
+    let app = App::default()
+        .at("/users/:id", get(EchoParam("id")).put(Text("updated")))
+        .put("/users/:name", Text("again"));
+    assert!(matches!(app.validate(), Err(Error::RouteConflict(_))));
+}
+
+#[test]
+fn test_routes_lists_registration_order() {
+    let app = App::default()
+        .get("/", Text("home"))
+        .at("/users/:id", get(EchoParam("id")).put(Text("updated")))
+        .name("user");
+
+    let routes: Vec<_> = app.routes().iter()
+        .map(|r| (r.method().clone(), r.pattern().as_str().to_string(), r.name()))
+        .collect();
+    assert_eq!(routes, vec![
+        (Method::GET, "/".to_string(), None),
+        (Method::GET, "/users/:id".to_string(), Some("user")),
+        (Method::PUT, "/users/:id".to_string(), Some("user")),
+    ]);
+}
+
+#[test]
+fn test_url_for() {
+    let app = App::default()
+        .get("/", Text("home"))
+        .name("home")
+        .get("/users/:id/posts/:slug", Text("post"))
+        .name("post")
+        .get("/files/*path", Text("file"))
+        .name("file");
+
+    assert_eq!(app.url_for("home", &[]).unwrap(), "/");
+    assert_eq!(
+        app.url_for("post", &[("id", "42"), ("slug", "a b/c?d")]).unwrap(),
+        "/users/42/posts/a%20b%2Fc%3Fd"
+    );
+    assert!(matches!(app.url_for("post", &[("id", ".."), ("slug", "x")]), Err(Error::UrlGeneration(_))));
+    assert!(matches!(app.url_for("file", &[("path", "docs/./a.txt")]), Err(Error::UrlGeneration(_))));
+    assert_eq!(app.url_for("file", &[("path", "docs/read me.txt")]).unwrap(), "/files/docs/read%20me.txt");
+    assert!(matches!(app.url_for("post", &[("id", "42")]), Err(Error::UrlGeneration(_))));
+    assert!(matches!(app.url_for("missing", &[]), Err(Error::UrlGeneration(_))));
+}
+
+#[monoio::test]
+async fn test_url_for_routes_back() {
+    let app = App::default()
+        .get("/users/:id", EchoParam("id"))
+        .name("user")
+        .get("/files/*path", EchoParam("path"))
+        .name("file");
+
+    for (name, param, value) in [
+        ("user", "id", "a b/c?d"),
+        ("user", "id", "..."),
+        ("user", "id", ".hidden"),
+        ("user", "id", "100%"),
+        ("file", "path", "docs/read me.txt"),
+    ] {
+        let url = app.url_for(name, &[(param, value)]).unwrap();
+        let req = Request::new(Method::GET, Uri::try_from(url.as_str()).unwrap());
+        let res = app.handle(req).await.ok().unwrap();
+        assert_eq!(res.body_str().unwrap(), value, "{}", url);
+    }
+}
+
+#[test]
+fn test_validate_rejects_duplicate_names() {
+    let app = App::default()
+        .get("/a", Text("a"))
+        .name("page")
+        .get("/b", Text("b"))
+        .name("page");
+    assert!(matches!(app.validate(), Err(Error::RouteConflict(_))));
+}
+
+struct EchoHost(&'static str);
+
+impl Service for EchoHost {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        res.set_body_str(&format!("{}:{}", req.host_param(self.0).unwrap_or(""), req.path_param("id").unwrap_or("")));
+        Ok(res)
+    }
+}
+
+async fn body_for_host<S: Service>(app: &App<(), S>, host: Option<&'static str>, path: &'static str) -> Result<String> {
+    let mut req = Request::new(Method::GET, Uri::from_static(path));
+    if let Some(host) = host {
+        req.headers_mut().insert("host", HeaderValue::from_static(host));
+    }
+    let res = app.handle(req).await?;
+    Ok(res.body_str().unwrap().to_string())
+}
+
+#[monoio::test]
+async fn test_host_router() {
+    let hosts = HostRouter::new()
+        .host(":tenant.example.com", Router::new().get("/items/:id", EchoHost("tenant")))
+        .host("api.example.com", Router::new().get("/items/:id", Text("api")))
+        .host("*", Text("fallback"));
+    assert!(hosts.validate().is_ok());
+    let app = App::with_empty_state(hosts);
+
+    assert_eq!(body_for_host(&app, Some("acme.example.com:8080"), "/items/3").await.unwrap(), "acme:3");
+    assert_eq!(body_for_host(&app, Some("API.example.com"), "/items/3").await.unwrap(), "api");
+    assert_eq!(body_for_host(&app, Some("example.com"), "/items/3").await.unwrap(), "fallback");
+    assert_eq!(body_for_host(&app, None, "/").await.unwrap(), "fallback");
+}
+
+#[test]
+fn test_host_router_validate() {
+    let hosts = HostRouter::new()
+        .host(":tenant.example.com", Text("a"))
+        .host(":org.example.com", Text("b"));
+    assert!(matches!(hosts.validate(), Err(Error::RouteConflict(_))));
+}
+
+#[test]
+fn test_normalize_path() {
+    assert_eq!(normalize_path("/"), "/");
+    assert_eq!(normalize_path("/a//b/../c/"), "/a/c");
+    assert_eq!(normalize_path("/a/./b/%2e%2E/c"), "/a/c");
+    assert_eq!(normalize_path("/../../etc/passwd"), "/etc/passwd");
+    assert_eq!(normalize_path("/a/.../b"), "/a/.../b");
+}
+
+#[monoio::test]
+async fn test_path_policy_lenient() {
+    let app = App::default()
+        .get("/custom", Text("custom"))
+        .get("/echo/:name", EchoParam("name"));
+    assert_eq!(body_of(&app, Method::GET, "/custom/").await.unwrap(), "custom");
+    assert_eq!(body_of(&app, Method::GET, "/echo//Alice/").await.unwrap(), "Alice");
+    assert_eq!(body_of(&app, Method::GET, "/echo/Bob/../Alice").await.unwrap(), "Alice");
+    assert_eq!(body_of(&app, Method::GET, "/echo/J%C3%BCrgen%20S").await.unwrap(), "J\u{fc}rgen S");
+}
+
+#[monoio::test]
+async fn test_path_policy_strict() {
+    let app = App::default()
+        .path_policy(PathPolicy::Strict)
+        .get("/custom", Text("custom"));
+    assert_eq!(body_of(&app, Method::GET, "/custom").await.unwrap(), "custom");
+    assert!(matches!(body_of(&app, Method::GET, "/custom/").await, Err(Error::NotFound)));
+    assert!(matches!(body_of(&app, Method::GET, "/x/../custom").await, Err(Error::NotFound)));
+}
+
+#[monoio::test]
+async fn test_path_policy_redirect() {
+    let app = App::default()
+        .path_policy(PathPolicy::Redirect)
+        .get("/echo/:name", EchoParam("name"));
+
+    let req = Request::new(Method::GET, Uri::from_static("/echo//Alice/?x=1"));
+    let res = app.handle(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(res.headers().get("location").unwrap(), "/echo/Alice?x=1");
+
+    assert_eq!(body_of(&app, Method::GET, "/echo/Alice").await.unwrap(), "Alice");
+    assert!(matches!(body_of(&app, Method::GET, "/other/").await, Err(Error::NotFound)));
+}
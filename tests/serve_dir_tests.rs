@@ -0,0 +1,185 @@
+use sark::{
+    app::App,
+    error::{Error, Result},
+    http::{FilePart, Request, Response},
+    service::{Router, ServeDir, ServeFile, Service},
+};
+use http::{HeaderValue, Method, StatusCode, Uri};
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("sark-serve-dir-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("docs")).unwrap();
+    std::fs::write(root.join("hello.txt"), "hello, world").unwrap();
+    std::fs::write(root.join("app.js"), "console.log(1)").unwrap();
+    std::fs::write(root.join("app.js.br"), "brotli bytes").unwrap();
+    std::fs::write(root.join("app.js.gz"), "gzip bytes").unwrap();
+    std::fs::write(root.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+    std::fs::write(root.parent().unwrap().join("sark-secret.txt"), "secret").unwrap();
+    root
+}
+
+fn files_app(root: &PathBuf) -> App<(), Router<(), impl sark::service::RouteNode<()>>> {
+    App::default()
+        .get("/static/*path", ServeDir::new(root).precompressed_br().precompressed_gzip())
+        .route(Method::HEAD, "/static/*path", ServeDir::new(root))
+        .get("/robots.txt", ServeFile::new(root.join("hello.txt")).content_type("text/plain"))
+}
+
+async fn send<S: Service>(app: &App<(), S>, method: Method, uri: &'static str, headers: &[(&'static str, &str)]) -> Result<Response> {
+    let mut req = Request::new(method, Uri::from_static(uri));
+    for (name, value) in headers {
+        req.headers_mut().insert(*name, HeaderValue::try_from(*value).unwrap());
+    }
+    app.handle(req).await
+}
+
+/// Files are sent as regions, ranges of several parts as regions between
+/// their headers.
+async fn body(res: &Response) -> String {
+    let mut out = res.body().to_vec();
+    for part in res.file_parts() {
+        match part {
+            FilePart::Bytes(bytes) => out.extend_from_slice(bytes),
+            FilePart::Region(region) => out.extend(region.read().await.unwrap()),
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[monoio::test]
+async fn test_serve_files() {
+    let root = fixture("files");
+    let app = files_app(&root);
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.file_body().unwrap().len(), 12);
+    assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+    assert_eq!(res.headers()["content-length"], "12");
+    assert_eq!(res.headers()["accept-ranges"], "bytes");
+    assert!(res.headers().contains_key("etag"));
+    assert!(res.headers().contains_key("last-modified"));
+    assert_eq!(body(&res).await, "hello, world");
+
+    let res = send(&app, Method::HEAD, "/static/hello.txt", &[]).await.ok().unwrap();
+    assert_eq!(res.headers()["content-length"], "12");
+    assert!(res.body().is_empty());
+    assert!(res.file_body().is_none());
+
+    let res = send(&app, Method::GET, "/static/docs", &[]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(res.headers()["location"], "/static/docs/");
+    let res = send(&app, Method::GET, "/static/docs?lang=en", &[]).await.ok().unwrap();
+    assert_eq!(res.headers()["location"], "/static/docs/?lang=en");
+
+    let res = send(&app, Method::GET, "/static/docs/", &[]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+    assert_eq!(body(&res).await, "<h1>docs</h1>");
+
+    let res = send(&app, Method::GET, "/robots.txt", &[]).await.ok().unwrap();
+    assert_eq!(res.headers()["content-type"], "text/plain");
+
+    for uri in ["/static/missing.txt", "/static/../sark-secret.txt", "/static/%2e%2e/sark-secret.txt", "/static/docs/..%2f..%2fsark-secret.txt"] {
+        let uri: &'static str = Box::leak(uri.to_string().into_boxed_str());
+        assert!(matches!(send(&app, Method::GET, uri, &[]).await, Err(Error::NotFound)), "{}", uri);
+    }
+}
+
+#[monoio::test]
+async fn test_conditional_requests() {
+    let root = fixture("conditional");
+    let app = files_app(&root);
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[]).await.ok().unwrap();
+    let etag = res.headers()["etag"].to_str().unwrap().to_string();
+    let modified = res.headers()["last-modified"].to_str().unwrap().to_string();
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("if-none-match", &format!("\"x\", W/{}", etag))]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()["etag"], etag.as_str());
+    assert!(res.body().is_empty());
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("if-modified-since", &modified)]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("if-none-match", "\"other\""), ("if-modified-since", &modified)]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("if-match", "\"other\"")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+}
+
+#[monoio::test]
+async fn test_range_requests() {
+    let root = fixture("ranges");
+    let app = files_app(&root);
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=0-4")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(res.headers()["content-range"], "bytes 0-4/12");
+    assert_eq!(body(&res).await, "hello");
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=-5")]).await.ok().unwrap();
+    assert_eq!(body(&res).await, "world");
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=7-100")]).await.ok().unwrap();
+    assert_eq!(res.headers()["content-range"], "bytes 7-11/12");
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=0-1, 7-8")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    let content_type = res.headers()["content-type"].to_str().unwrap();
+    let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+    let expected = format!(
+        "--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/12\r\n\r\nhe\r\n\
+         --{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 7-8/12\r\n\r\nwo\r\n--{b}--\r\n",
+        b = boundary,
+    );
+    assert!(res.body().is_empty());
+    assert!(res.file_parts().iter().any(|part| matches!(part, FilePart::Region(_))));
+    assert_eq!(body(&res).await, expected);
+    assert_eq!(res.headers()["content-length"], expected.len().to_string().as_str());
+
+    let res = send(&app, Method::HEAD, "/static/hello.txt", &[("range", "bytes=0-1, 7-8")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(res.headers()["content-length"], expected.len().to_string().as_str());
+    assert!(res.body().is_empty());
+    assert!(res.file_parts().is_empty());
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=50-")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(res.headers()["content-range"], "bytes */12");
+    assert_eq!(res.headers()["accept-ranges"], "bytes");
+    assert!(res.headers().contains_key("etag"));
+    assert!(res.headers().contains_key("last-modified"));
+    assert!(res.body().is_empty());
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("range", "bytes=0-4"), ("if-range", "\"stale\"")]).await.ok().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body(&res).await, "hello, world");
+}
+
+#[monoio::test]
+async fn test_precompressed_variants() {
+    let root = fixture("precompressed");
+    let app = files_app(&root);
+
+    let res = send(&app, Method::GET, "/static/app.js", &[("accept-encoding", "gzip, br")]).await.ok().unwrap();
+    assert_eq!(res.headers()["content-encoding"], "br");
+    assert_eq!(res.headers()["content-type"], "text/javascript; charset=utf-8");
+    assert_eq!(res.headers()["vary"], "accept-encoding");
+    assert_eq!(body(&res).await, "brotli bytes");
+
+    let res = send(&app, Method::GET, "/static/app.js", &[("accept-encoding", "gzip")]).await.ok().unwrap();
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert_eq!(body(&res).await, "gzip bytes");
+
+    let res = send(&app, Method::GET, "/static/app.js", &[]).await.ok().unwrap();
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(body(&res).await, "console.log(1)");
+
+    let res = send(&app, Method::GET, "/static/hello.txt", &[("accept-encoding", "br")]).await.ok().unwrap();
+    assert!(res.headers().get("content-encoding").is_none());
+}
@@ -0,0 +1,409 @@
+use sark::{
+    app::App,
+    error::{Error, Result},
+    http::{BodyMode, Request, Response, TrustedProxies},
+    server::Server,
+    service::{ServeFile, Service},
+};
+use http::{HeaderValue, StatusCode};
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio::net::TcpStream;
+use std::time::Duration;
+
+fn start<S: Service + 'static>(port: u16, app: App<(), S>) -> String {
+    start_server(Server::bind(format!("127.0.0.1:{}", port)), port, app)
+}
+
+fn start_server<S: Service + 'static>(server: Server, port: u16, app: App<(), S>) -> String {
+    let app: &'static App<(), S> = Box::leak(Box::new(app));
+    monoio::spawn(async move { server.serve(app).await });
+    format!("127.0.0.1:{}", port)
+}
+
+async fn roundtrip(addr: &str, request: &'static str) -> Vec<u8> {
+    monoio::time::sleep(Duration::from_millis(20)).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let (result, _) = stream.write_all(request.as_bytes().to_vec()).await;
+    result.unwrap();
+
+    let mut response = Vec::new();
+    loop {
+        let (result, buf) = stream.read(vec![0u8; 4096]).await;
+        let n = result.unwrap();
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    response
+}
+
+struct HeaderEcho;
+
+impl Service for HeaderEcho {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        for value in req.headers().get_all("x-forwarded-for") {
+            res.headers_mut().append("x-seen", value.clone());
+        }
+        for value in req.headers().get_all("x-empty") {
+            res.headers_mut().append("x-empty-seen", value.clone());
+        }
+        res.headers_mut().insert("x-opaque", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        Ok(res)
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_repeated_and_opaque_headers() {
+    let addr = start(38411, App::default().get("/", HeaderEcho));
+    let response = roundtrip(
+        &addr,
+        "GET / HTTP/1.1\r\nHost: x\r\nX-Forwarded-For: 10.0.0.1\r\nX-Empty: \r\nX-Forwarded-For: 10.0.0.2\r\n\r\n",
+    ).await;
+
+    let head = response.split(|b| *b == b'\n').collect::<Vec<_>>();
+    assert!(head.contains(&&b"x-seen: 10.0.0.1\r"[..]));
+    assert!(head.contains(&&b"x-seen: 10.0.0.2\r"[..]));
+    assert!(head.contains(&&b"x-empty-seen: \r"[..]));
+    assert!(head.contains(&&b"x-opaque: caf\xe9\r"[..]));
+}
+
+struct PeerEcho;
+
+impl Service for PeerEcho {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let info = req.connection_info().unwrap();
+        let mut res = Response::ok();
+        res.set_body_str(&format!(
+            "{} {} {} {} {}",
+            info.id(), info.remote_addr().ip(), info.local_addr(), req.client_ip().unwrap(), info.tls().is_none()
+        ));
+        Ok(res)
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_connection_info() {
+    let server = Server::bind("127.0.0.1:38412")
+        .trusted_proxies(TrustedProxies::new().trust("127.0.0.1").unwrap());
+    let addr = start_server(server, 38412, App::default().get("/", PeerEcho));
+
+    let response = roundtrip(&addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    assert!(response.ends_with(b"\r\n\r\n1 127.0.0.1 127.0.0.1:38412 127.0.0.1 true"));
+
+    let response = roundtrip(&addr, "GET / HTTP/1.1\r\nX-Forwarded-For: 198.51.100.7\r\n\r\n").await;
+    assert!(response.ends_with(b"\r\n\r\n2 127.0.0.1 127.0.0.1:38412 198.51.100.7 true"));
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_errors_are_rendered() {
+    let addr = start(38413, App::default().get("/", PeerEcho));
+    let response = roundtrip(&addr, "GET /missing HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+    assert!(response.ends_with(br#"{"title":"Not Found","status":404,"detail":"Not found"}"#));
+}
+
+/// Files are spliced to the socket under io_uring and copied in chunks
+/// under the legacy driver; both must send the same bytes.
+async fn check_file_bodies(port: u16) {
+    let path = std::env::temp_dir().join(format!("sark-server-file-{}-{}.bin", port, std::process::id()));
+    let contents = (0..3_000_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    std::fs::write(&path, &contents).unwrap();
+    let addr = start(port, App::default().get("/file", ServeFile::new(&path)));
+
+    let response = roundtrip(&addr, "GET /file HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = std::str::from_utf8(&response[..split]).unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(head.contains("content-length: 3000000\r\n"));
+    assert!(response[split..] == contents[..]);
+
+    let response = roundtrip(&addr, "GET /file HTTP/1.1\r\nRange: bytes=1000-1009\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 206 Partial Content\r\n"));
+    assert!(response.ends_with(&contents[1000..1010]));
+
+    // Several chunks from an offset that is not page aligned.
+    let response = roundtrip(&addr, "GET /file HTTP/1.1\r\nRange: bytes=12345-2012344\r\n\r\n").await;
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    assert!(response.starts_with(b"HTTP/1.1 206 Partial Content\r\n"));
+    assert!(response[split..] == contents[12345..2012345]);
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_file_bodies_are_sent() {
+    check_file_bodies(38414).await;
+}
+
+#[monoio::test(driver = "legacy", timer_enabled = true)]
+async fn test_file_bodies_are_sent_on_legacy_driver() {
+    check_file_bodies(38421).await;
+}
+
+struct LargeBody;
+
+impl Service for LargeBody {
+    async fn call(&self, _req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        res.set_body(&large_body()[..]);
+        Ok(res)
+    }
+}
+
+fn large_body() -> Vec<u8> {
+    (0..8_000_000u32).map(|i| (i % 241) as u8).collect()
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_large_bodies_are_written_whole() {
+    let addr = start(38415, App::default().get("/", LargeBody));
+    let response = roundtrip(&addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    let head = std::str::from_utf8(&response[..split]).unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(head.ends_with("\r\nContent-Length: 8000000\r\n\r\n"));
+    assert!(response[split..] == large_body()[..]);
+}
+
+struct Branded;
+
+impl Service for Branded {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let mut res = Response::ok();
+        if req.uri().path() == "/custom" {
+            res.headers_mut().insert("server", HeaderValue::from_static("custom"));
+            res.headers_mut().insert("date", HeaderValue::from_static("Thu, 01 Jan 1970 00:00:00 GMT"));
+        }
+        Ok(res)
+    }
+}
+
+fn header_lines(response: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(response).split("\r\n").map(str::to_string).collect()
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_date_and_server_headers() {
+    let server = Server::bind("127.0.0.1:38416").server_header(HeaderValue::from_static("sark"));
+    let addr = start_server(server, 38416, App::default().get("/", Branded).get("/custom", Branded));
+
+    let lines = header_lines(&roundtrip(&addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n").await);
+    assert!(lines.contains(&"Server: sark".to_string()));
+    let date = lines.iter().find_map(|line| line.strip_prefix("Date: ")).unwrap();
+    let sent = httpdate::parse_http_date(date).unwrap();
+    let skew = std::time::SystemTime::now().duration_since(sent).unwrap();
+    assert!(skew < Duration::from_secs(5));
+
+    let lines = header_lines(&roundtrip(&addr, "GET /custom HTTP/1.1\r\nHost: x\r\n\r\n").await);
+    assert!(lines.contains(&"server: custom".to_string()));
+    assert!(lines.contains(&"date: Thu, 01 Jan 1970 00:00:00 GMT".to_string()));
+    assert!(!lines.iter().any(|line| line.starts_with("Server: ") || line.starts_with("Date: ")));
+}
+
+struct Bodiless;
+
+impl Service for Bodiless {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        match req.uri().path() {
+            "/cached" => {
+                let mut res = Response::new(StatusCode::NOT_MODIFIED);
+                res.headers_mut().insert("etag", HeaderValue::from_static("\"v1\""));
+                Ok(res)
+            }
+            _ => Ok(Response::no_content()),
+        }
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_bodiless_responses_have_no_content_length() {
+    let addr = start(38420, App::default().get("/cached", Bodiless).delete("/item", Bodiless));
+
+    let response = roundtrip(&addr, "GET /cached HTTP/1.1\r\nIf-None-Match: \"v1\"\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 304 Not Modified\r\n"));
+    assert!(response.ends_with(b"\r\n\r\n"));
+    let lines = header_lines(&response);
+    assert!(lines.contains(&"etag: \"v1\"".to_string()));
+    assert!(!lines.iter().any(|line| line.to_ascii_lowercase().starts_with("content-length:")));
+
+    let response = roundtrip(&addr, "DELETE /item HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 204 No Content\r\n"));
+    assert!(!header_lines(&response).iter().any(|line| line.to_ascii_lowercase().starts_with("content-length:")));
+}
+
+struct Upload;
+
+impl Service for Upload {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        Ok(Response::text(format!("got {} bytes", req.body().len())))
+    }
+
+    async fn before_body(&self, req: &Request, _state: &()) -> Result<BodyMode> {
+        match req.headers().get("authorization") {
+            Some(_) => Ok(BodyMode::Buffer),
+            None => Err(Error::Unauthorized("missing credentials".into())),
+        }
+    }
+}
+
+struct Closed;
+
+impl Service for Closed {
+    async fn call(&self, _req: Request, _state: &()) -> Result<Response> {
+        Ok(Response::ok())
+    }
+
+    async fn before_body(&self, _req: &Request, _state: &()) -> Result<BodyMode> {
+        Err(Error::Forbidden("uploads are closed".into()))
+    }
+}
+
+async fn read_some(stream: &mut TcpStream) -> Vec<u8> {
+    let (result, buf) = stream.read(vec![0u8; 4096]).await;
+    let n = result.unwrap();
+    buf[..n].to_vec()
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_expect_continue() {
+    let server = Server::bind("127.0.0.1:38417").max_body_size(1024);
+    let app = App::default().post("/upload", Upload).post("/closed", Closed);
+    let addr = start_server(server, 38417, app);
+    monoio::time::sleep(Duration::from_millis(20)).await;
+
+    let mut stream = TcpStream::connect(&addr).await.unwrap();
+    let head = "POST /upload HTTP/1.1\r\nAuthorization: token\r\nContent-Length: 10\r\nExpect: 100-continue\r\n\r\n";
+    let (result, _) = stream.write_all(head.as_bytes().to_vec()).await;
+    result.unwrap();
+    assert_eq!(read_some(&mut stream).await, b"HTTP/1.1 100 Continue\r\n\r\n");
+    let (result, _) = stream.write_all(b"0123456789".to_vec()).await;
+    result.unwrap();
+    let response = read_some(&mut stream).await;
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(b"got 10 bytes"));
+
+    let response = roundtrip(&addr, "POST /upload HTTP/1.1\r\nContent-Length: 10\r\nExpect: 100-continue\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 401 Unauthorized\r\n"));
+
+    let response = roundtrip(&addr, "POST /closed HTTP/1.1\r\nAuthorization: token\r\nContent-Length: 10\r\nExpect: 100-continue\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+
+    let response = roundtrip(&addr, "POST /upload HTTP/1.1\r\nAuthorization: token\r\nContent-Length: 5000\r\nExpect: 100-continue\r\n\r\n").await;
+    assert!(response.starts_with(b"HTTP/1.1 413 Payload Too Large\r\n"));
+
+    let response = roundtrip(&addr, "POST /upload HTTP/1.1\r\nContent-Length: 3\r\nExpect: something-else\r\n\r\nabc").await;
+    assert!(response.starts_with(b"HTTP/1.1 417 Expectation Failed\r\n"));
+
+    let response = roundtrip(&addr, "POST /upload HTTP/1.1\r\nAuthorization: token\r\nContent-Length: 3\r\n\r\nabc").await;
+    assert!(response.ends_with(b"got 3 bytes"));
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_rejected_bodies_are_drained() {
+    let server = Server::bind("127.0.0.1:38419").max_body_size(1024);
+    let addr = start_server(server, 38419, App::default().post("/closed", Closed).post("/upload", Upload));
+    monoio::time::sleep(Duration::from_millis(20)).await;
+
+    for path in ["/closed", "/upload"] {
+        let body = vec![b'x'; 300 * 1024];
+        let mut request = format!("POST {} HTTP/1.1\r\nAuthorization: token\r\nContent-Length: {}\r\n\r\n", path, body.len()).into_bytes();
+        request.extend_from_slice(&body);
+
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        let (result, _) = stream.write_all(request).await;
+        result.unwrap();
+        let mut response = Vec::new();
+        loop {
+            let (result, buf) = stream.read(vec![0u8; 4096]).await;
+            let n = result.unwrap();
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        let expected: &[u8] = if path == "/closed" { b"HTTP/1.1 403 Forbidden\r\n" } else { b"HTTP/1.1 413 Payload Too Large\r\n" };
+        assert!(response.starts_with(expected), "{}", path);
+    }
+}
+
+async fn read_to_end(stream: &mut TcpStream) -> Vec<u8> {
+    let mut response = Vec::new();
+    loop {
+        let (result, buf) = stream.read(vec![0u8; 4096]).await;
+        match result.unwrap() {
+            0 => return response,
+            n => response.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_stalled_bodies_do_not_hold_up_the_server() {
+    let addr = start(38422, App::default().post("/closed", Closed).get("/", Branded));
+    monoio::time::sleep(Duration::from_millis(20)).await;
+
+    // Still waiting for 100 Continue: nothing is drained.
+    let mut waiting = TcpStream::connect(&addr).await.unwrap();
+    let head = "POST /closed HTTP/1.1\r\nContent-Length: 100000\r\nExpect: 100-continue\r\n\r\n";
+    let (result, _) = waiting.write_all(head.as_bytes().to_vec()).await;
+    result.unwrap();
+    let response = monoio::time::timeout(Duration::from_millis(500), read_to_end(&mut waiting)).await.unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+    assert!(header_lines(&response).contains(&"connection: close".to_string()));
+    let response = monoio::time::timeout(Duration::from_millis(500), roundtrip(&addr, "GET / HTTP/1.1\r\n\r\n")).await.unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    // Sent part of the body, then stalled: drained until the timeout.
+    let mut stalled = TcpStream::connect(&addr).await.unwrap();
+    let head = "POST /closed HTTP/1.1\r\nContent-Length: 100000\r\n\r\npartial body";
+    let (result, _) = stalled.write_all(head.as_bytes().to_vec()).await;
+    result.unwrap();
+    let response = read_some(&mut stalled).await;
+    assert!(response.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+    assert!(header_lines(&response).contains(&"connection: close".to_string()));
+    let response = monoio::time::timeout(Duration::from_secs(3), roundtrip(&addr, "GET / HTTP/1.1\r\n\r\n")).await.unwrap();
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    drop(waiting);
+    drop(stalled);
+}
+
+struct StreamedUpload;
+
+impl Service for StreamedUpload {
+    async fn call(&self, mut req: Request, _state: &()) -> Result<Response> {
+        let mut multipart = req.multipart()?;
+        let mut names = Vec::new();
+        while let Some(part) = multipart.next_part().await? {
+            names.push(format!("{}={}", part.name().unwrap_or_default(), part.data().len()));
+        }
+        Ok(Response::text(names.join(",")))
+    }
+
+    async fn before_body(&self, _req: &Request, _state: &()) -> Result<BodyMode> {
+        Ok(BodyMode::Stream)
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn test_multipart_is_streamed() {
+    let server = Server::bind("127.0.0.1:38418").max_body_size(16);
+    let addr = start_server(server, 38418, App::default().post("/upload", StreamedUpload));
+    monoio::time::sleep(Duration::from_millis(20)).await;
+
+    let body = "--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nfirst part\r\n\
+                --b\r\nContent-Disposition: form-data; name=\"b\"\r\n\r\nsecond\r\n--b--\r\n";
+    let head = format!(
+        "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    );
+    let mut stream = TcpStream::connect(&addr).await.unwrap();
+    let (result, _) = stream.write_all(format!("{}{}", head, &body[..30]).into_bytes()).await;
+    result.unwrap();
+    monoio::time::sleep(Duration::from_millis(20)).await;
+    let (result, _) = stream.write_all(body.as_bytes()[30..].to_vec()).await;
+    result.unwrap();
+
+    let response = read_some(&mut stream).await;
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(b"a=10,b=6"));
+}
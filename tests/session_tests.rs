@@ -0,0 +1,135 @@
+use sark::{
+    app::App,
+    error::Result,
+    http::{Request, Response},
+    service::{Router, Service},
+    session::{MemoryStore, SessionConfig, SessionService},
+};
+use http::{Method, Uri, HeaderValue};
+use std::cell::Cell;
+use std::time::{Duration, SystemTime};
+
+struct Login;
+
+impl Service for Login {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let session = req.session().unwrap();
+        session.regenerate();
+        session.insert("user_id", 42u32)?;
+        Ok(Response::ok())
+    }
+}
+
+struct Whoami;
+
+impl Service for Whoami {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        let user_id: Option<u32> = req.session().unwrap().get("user_id");
+        let mut res = Response::ok();
+        res.set_body_str(&user_id.map(|id| id.to_string()).unwrap_or_else(|| "anonymous".into()));
+        Ok(res)
+    }
+}
+
+struct Logout;
+
+impl Service for Logout {
+    async fn call(&self, req: Request, _state: &()) -> Result<Response> {
+        req.session().unwrap().destroy();
+        Ok(Response::ok())
+    }
+}
+
+fn session_app(config: SessionConfig) -> App<(), SessionService<impl Service, MemoryStore>> {
+    let router = Router::new()
+        .post("/login", Login)
+        .get("/whoami", Whoami)
+        .post("/logout", Logout);
+    App::with_empty_state(SessionService::new(router, MemoryStore::new()).config(config))
+}
+
+async fn send<S: Service>(app: &App<(), S>, method: Method, path: &'static str, sid: Option<&str>) -> Response {
+    let mut req = Request::new(method, Uri::from_static(path));
+    if let Some(sid) = sid {
+        req.headers_mut().insert("cookie", HeaderValue::try_from(format!("sark.sid={}", sid)).unwrap());
+    }
+    app.handle(req).await.unwrap()
+}
+
+fn session_id(res: &Response) -> Option<String> {
+    let value = res.headers().get("set-cookie")?.to_str().unwrap();
+    let pair = value.split(';').next().unwrap();
+    Some(pair.strip_prefix("sark.sid=").unwrap().to_string())
+}
+
+#[monoio::test]
+async fn test_session_lifecycle() {
+    let app = session_app(SessionConfig::new());
+
+    let res = send(&app, Method::GET, "/whoami", None).await;
+    assert_eq!(res.body_str(), Some("anonymous"));
+    assert!(session_id(&res).is_none());
+    assert!(app.service().store().is_empty());
+
+    let res = send(&app, Method::POST, "/login", Some("planted")).await;
+    let sid = session_id(&res).unwrap();
+    assert_ne!(sid, "planted");
+    assert!(res.headers().get("set-cookie").unwrap().to_str().unwrap().contains("HttpOnly"));
+
+    let res = send(&app, Method::GET, "/whoami", Some(&sid)).await;
+    assert_eq!(res.body_str(), Some("42"));
+    assert!(session_id(&res).is_none());
+
+    let res = send(&app, Method::POST, "/login", Some(&sid)).await;
+    let regenerated = session_id(&res).unwrap();
+    assert_ne!(regenerated, sid);
+    assert_eq!(send(&app, Method::GET, "/whoami", Some(&sid)).await.body_str(), Some("anonymous"));
+    assert_eq!(app.service().store().len(), 1);
+
+    let res = send(&app, Method::POST, "/logout", Some(&regenerated)).await;
+    assert_eq!(session_id(&res).as_deref(), Some(""));
+    assert!(app.service().store().is_empty());
+}
+
+thread_local! {
+    static NOW: Cell<SystemTime> = Cell::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+}
+
+fn fake_now() -> SystemTime {
+    NOW.with(Cell::get)
+}
+
+fn advance(by: Duration) {
+    NOW.with(|now| now.set(now.get() + by));
+}
+
+#[monoio::test]
+async fn test_session_expiry() {
+    let app = session_app(SessionConfig::new().clock(fake_now).idle_timeout(Duration::from_secs(10)));
+    let sid = session_id(&send(&app, Method::POST, "/login", None).await).unwrap();
+    advance(Duration::from_secs(9));
+    assert_eq!(send(&app, Method::GET, "/whoami", Some(&sid)).await.body_str(), Some("42"));
+    advance(Duration::from_secs(11));
+    assert_eq!(send(&app, Method::GET, "/whoami", Some(&sid)).await.body_str(), Some("anonymous"));
+
+    let app = session_app(SessionConfig::new().clock(fake_now).absolute_timeout(Duration::from_secs(200)));
+    let sid = session_id(&send(&app, Method::POST, "/login", None).await).unwrap();
+    for _ in 0..2 {
+        advance(Duration::from_secs(90));
+        assert_eq!(send(&app, Method::GET, "/whoami", Some(&sid)).await.body_str(), Some("42"));
+    }
+    advance(Duration::from_secs(20));
+    assert_eq!(send(&app, Method::GET, "/whoami", Some(&sid)).await.body_str(), Some("anonymous"));
+}
+
+#[monoio::test]
+async fn test_purge_uses_session_clock() {
+    let app = session_app(SessionConfig::new().clock(fake_now).idle_timeout(Duration::from_secs(10)));
+    send(&app, Method::POST, "/login", None).await;
+    app.service().purge_expired();
+    assert_eq!(app.service().store().len(), 1);
+
+    advance(Duration::from_secs(10));
+    app.service().purge_expired();
+    assert!(app.service().store().is_empty());
+}